@@ -1,65 +1,155 @@
 // Generated by `wit-bindgen` 0.21.0. DO NOT EDIT!
 // Options used:
-#[doc(hidden)]
-#[allow(non_snake_case)]
-pub unsafe fn _export_eval_cabi<T: Guest>(arg0: *mut u8, arg1: usize) -> *mut u8 {
-    let base3 = arg0;
-    let len3 = arg1;
-    let mut result3 = _rt::Vec::with_capacity(len3);
-    for i in 0..len3 {
-        let base = base3.add(i * 8);
-        let e3 = {
-            let l0 = *base.add(0).cast::<*mut u8>();
-            let l1 = *base.add(4).cast::<usize>();
-            let len2 = l1;
-            let bytes2 = _rt::Vec::from_raw_parts(l0.cast(), len2, len2);
-
-            _rt::string_lift(bytes2)
-        };
-        result3.push(e3);
-    }
-    _rt::cabi_dealloc(base3, len3 * 8, 4);
-    let result4 = T::eval(result3);
-    let ptr5 = _RET_AREA.0.as_mut_ptr().cast::<u8>();
-    let vec6 = (result4.into_bytes()).into_boxed_slice();
-    let ptr6 = vec6.as_ptr().cast::<u8>();
-    let len6 = vec6.len();
-    ::core::mem::forget(vec6);
-    *ptr5.add(4).cast::<usize>() = len6;
-    *ptr5.add(0).cast::<*mut u8>() = ptr6.cast_mut();
-    ptr5
-}
-#[doc(hidden)]
-#[allow(non_snake_case)]
-pub unsafe fn __post_return_eval<T: Guest>(arg0: *mut u8) {
-    let l0 = *arg0.add(0).cast::<*mut u8>();
-    let l1 = *arg0.add(4).cast::<usize>();
-    _rt::cabi_dealloc(l0, l1, 1);
-}
-pub trait Guest {
-    fn eval(expr: _rt::Vec<_rt::String>) -> _rt::String;
-}
-#[doc(hidden)]
+pub mod exports {
+    pub mod wasi_repl {
+        pub mod host {
+            #[allow(clippy::all)]
+            pub mod command {
+                #[used]
+                #[doc(hidden)]
+                #[cfg(target_arch = "wasm32")]
+                static __FORCE_SECTION_REF: fn() =
+                    super::super::super::super::__link_custom_section_describing_imports;
+                use super::super::super::super::_rt;
+                /// How a command finished.
+                #[derive(Clone)]
+                pub struct Outcome {
+                    /// Its output, or why it failed.
+                    pub output: Result<_rt::String, _rt::String>,
+                    /// Its exit status if it failed, which the REPL keeps in `$?`.
+                    /// Zero is taken as one. Ignored on success.
+                    pub status: u8,
+                    /// Text for the REPL to show on stderr, labelled with the
+                    /// command's name, whether or not it failed.
+                    pub stderr: Option<_rt::String>,
+                }
+                impl ::core::fmt::Debug for Outcome {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        f.debug_struct("Outcome")
+                            .field("output", &self.output)
+                            .field("status", &self.status)
+                            .field("stderr", &self.stderr)
+                            .finish()
+                    }
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn _export_eval_cabi<T: Guest>(arg0: *mut u8, arg1: usize) -> *mut u8 {
+                    let base3 = arg0;
+                    let len3 = arg1;
+                    let mut result3 = _rt::Vec::with_capacity(len3);
+                    for i in 0..len3 {
+                        let base = base3.add(i * 8);
+                        let e3 = {
+                            let l0 = *base.add(0).cast::<*mut u8>();
+                            let l1 = *base.add(4).cast::<usize>();
+                            let len2 = l1;
+                            let bytes2 = _rt::Vec::from_raw_parts(l0.cast(), len2, len2);
+
+                            _rt::string_lift(bytes2)
+                        };
+                        result3.push(e3);
+                    }
+                    _rt::cabi_dealloc(base3, len3 * 8, 4);
+                    let result4 = T::eval(result3);
+                    let ptr5 = _RET_AREA.0.as_mut_ptr().cast::<u8>();
+                    let Outcome {
+                        output: output6,
+                        status: status6,
+                        stderr: stderr6,
+                    } = result4;
+                    match output6 {
+                        Ok(e) => {
+                            *ptr5.add(0).cast::<u8>() = (0i32) as u8;
+                            let vec7 = (e.into_bytes()).into_boxed_slice();
+                            let ptr7 = vec7.as_ptr().cast::<u8>();
+                            let len7 = vec7.len();
+                            ::core::mem::forget(vec7);
+                            *ptr5.add(8).cast::<usize>() = len7;
+                            *ptr5.add(4).cast::<*mut u8>() = ptr7.cast_mut();
+                        }
+                        Err(e) => {
+                            *ptr5.add(0).cast::<u8>() = (1i32) as u8;
+                            let vec8 = (e.into_bytes()).into_boxed_slice();
+                            let ptr8 = vec8.as_ptr().cast::<u8>();
+                            let len8 = vec8.len();
+                            ::core::mem::forget(vec8);
+                            *ptr5.add(8).cast::<usize>() = len8;
+                            *ptr5.add(4).cast::<*mut u8>() = ptr8.cast_mut();
+                        }
+                    };
+                    *ptr5.add(12).cast::<u8>() = (_rt::as_i32(status6)) as u8;
+                    match stderr6 {
+                        Some(e) => {
+                            *ptr5.add(16).cast::<u8>() = (1i32) as u8;
+                            let vec9 = (e.into_bytes()).into_boxed_slice();
+                            let ptr9 = vec9.as_ptr().cast::<u8>();
+                            let len9 = vec9.len();
+                            ::core::mem::forget(vec9);
+                            *ptr5.add(24).cast::<usize>() = len9;
+                            *ptr5.add(20).cast::<*mut u8>() = ptr9.cast_mut();
+                        }
+                        None => {
+                            *ptr5.add(16).cast::<u8>() = (0i32) as u8;
+                        }
+                    };
+                    ptr5
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn __post_return_eval<T: Guest>(arg0: *mut u8) {
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => {
+                            let l1 = *arg0.add(4).cast::<*mut u8>();
+                            let l2 = *arg0.add(8).cast::<usize>();
+                            _rt::cabi_dealloc(l1, l2, 1);
+                        }
+                        _ => {
+                            let l3 = *arg0.add(4).cast::<*mut u8>();
+                            let l4 = *arg0.add(8).cast::<usize>();
+                            _rt::cabi_dealloc(l3, l4, 1);
+                        }
+                    }
+                    let l5 = i32::from(*arg0.add(16).cast::<u8>());
+                    match l5 {
+                        0 => (),
+                        _ => {
+                            let l6 = *arg0.add(20).cast::<*mut u8>();
+                            let l7 = *arg0.add(24).cast::<usize>();
+                            _rt::cabi_dealloc(l6, l7, 1);
+                        }
+                    }
+                }
+                pub trait Guest {
+                    fn eval(args: _rt::Vec<_rt::String>) -> Outcome;
+                }
+                #[doc(hidden)]
 
-macro_rules! __export_world_ipfs_cabi{
-  ($ty:ident with_types_in $($path_to_types:tt)*) => (const _: () = {
+macro_rules! __export_wasi_repl_host_command_0_2_0_cabi{
+      ($ty:ident with_types_in $($path_to_types:tt)*) => (const _: () = {
 
-    #[export_name = "eval"]
-    unsafe extern "C" fn export_eval(arg0: *mut u8,arg1: usize,) -> *mut u8 {
-      $($path_to_types)*::_export_eval_cabi::<$ty>(arg0, arg1)
+        #[export_name = "wasi-repl:host/command@0.2.0#eval"]
+        unsafe extern "C" fn export_eval(arg0: *mut u8,arg1: usize,) -> *mut u8 {
+          $($path_to_types)*::_export_eval_cabi::<$ty>(arg0, arg1)
+        }
+        #[export_name = "cabi_post_wasi-repl:host/command@0.2.0#eval"]
+        unsafe extern "C" fn _post_return_eval(arg0: *mut u8,) {
+          $($path_to_types)*::__post_return_eval::<$ty>(arg0)
+        }
+      };);
     }
-    #[export_name = "cabi_post_eval"]
-    unsafe extern "C" fn _post_return_eval(arg0: *mut u8,) {
-      $($path_to_types)*::__post_return_eval::<$ty>(arg0)
+                #[doc(hidden)]
+                pub(crate) use __export_wasi_repl_host_command_0_2_0_cabi;
+                #[repr(align(4))]
+                struct _RetArea([::core::mem::MaybeUninit<u8>; 28]);
+                static mut _RET_AREA: _RetArea = _RetArea([::core::mem::MaybeUninit::uninit(); 28]);
+            }
+        }
     }
-  };);
 }
-#[doc(hidden)]
-pub(crate) use __export_world_ipfs_cabi;
-#[repr(align(4))]
-struct _RetArea([::core::mem::MaybeUninit<u8>; 8]);
-static mut _RET_AREA: _RetArea = _RetArea([::core::mem::MaybeUninit::uninit(); 8]);
 mod _rt {
+    pub use alloc_crate::string::String;
     pub use alloc_crate::vec::Vec;
     pub unsafe fn string_lift(bytes: Vec<u8>) -> String {
         if cfg!(debug_assertions) {
@@ -75,7 +165,75 @@ mod _rt {
         let layout = alloc::Layout::from_size_align_unchecked(size, align);
         alloc::dealloc(ptr as *mut u8, layout);
     }
-    pub use alloc_crate::string::String;
+    pub fn as_i32<T: AsI32>(t: T) -> i32 {
+        t.as_i32()
+    }
+
+    pub trait AsI32 {
+        fn as_i32(self) -> i32;
+    }
+
+    impl<'a, T: Copy + AsI32> AsI32 for &'a T {
+        fn as_i32(self) -> i32 {
+            (*self).as_i32()
+        }
+    }
+
+    impl AsI32 for i32 {
+        #[inline]
+        fn as_i32(self) -> i32 {
+            self as i32
+        }
+    }
+
+    impl AsI32 for u32 {
+        #[inline]
+        fn as_i32(self) -> i32 {
+            self as i32
+        }
+    }
+
+    impl AsI32 for i16 {
+        #[inline]
+        fn as_i32(self) -> i32 {
+            self as i32
+        }
+    }
+
+    impl AsI32 for u16 {
+        #[inline]
+        fn as_i32(self) -> i32 {
+            self as i32
+        }
+    }
+
+    impl AsI32 for i8 {
+        #[inline]
+        fn as_i32(self) -> i32 {
+            self as i32
+        }
+    }
+
+    impl AsI32 for u8 {
+        #[inline]
+        fn as_i32(self) -> i32 {
+            self as i32
+        }
+    }
+
+    impl AsI32 for char {
+        #[inline]
+        fn as_i32(self) -> i32 {
+            self as i32
+        }
+    }
+
+    impl AsI32 for usize {
+        #[inline]
+        fn as_i32(self) -> i32 {
+            self as i32
+        }
+    }
     extern crate alloc as alloc_crate;
     pub use alloc_crate::alloc;
 }
@@ -99,23 +257,26 @@ mod _rt {
 #[allow(unused_macros)]
 #[doc(hidden)]
 
-macro_rules! __export_ipfs_impl {
+macro_rules! __export_command_world_impl {
   ($ty:ident) => (self::export!($ty with_types_in self););
   ($ty:ident with_types_in $($path_to_types_root:tt)*) => (
-  $($path_to_types_root)*::__export_world_ipfs_cabi!($ty with_types_in $($path_to_types_root)*);
+  $($path_to_types_root)*::exports::wasi_repl::host::command::__export_wasi_repl_host_command_0_2_0_cabi!($ty with_types_in $($path_to_types_root)*::exports::wasi_repl::host::command);
   )
 }
 #[doc(inline)]
-pub(crate) use __export_ipfs_impl as export;
+pub(crate) use __export_command_world_impl as export;
 
 #[cfg(target_arch = "wasm32")]
-#[link_section = "component-type:wit-bindgen:0.21.0:ipfs:encoded world"]
+#[link_section = "component-type:wit-bindgen:0.21.0:command-world:encoded world"]
 #[doc(hidden)]
-pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; 175] = *b"\
-\0asm\x0d\0\x01\0\0\x19\x16wit-component-encoding\x04\0\x075\x01A\x02\x01A\x03\x01\
-ps\x01@\x01\x04expr\0\0s\x04\0\x04eval\x01\x01\x04\x01\x13component:echo/ipfs\x04\
-\0\x0b\x0a\x01\0\x04ipfs\x03\0\0\0G\x09producers\x01\x0cprocessed-by\x02\x0dwit-\
-component\x070.201.0\x10wit-bindgen-rust\x060.21.0";
+pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; 285] = *b"\
+\0asm\x0d\0\x01\0\0\x19\x16wit-component-encoding\x04\0\x07\x99\x01\x01A\x02\x01\
+A\x02\x01B\x07\x01j\x01s\x01s\x01ks\x01r\x03\x06output\0\x06status}\x06stderr\
+\x01\x04\0\x07outcome\x03\0\x02\x01ps\x01@\x01\x04args\x04\0\x03\x04\0\x04eval\
+\x01\x05\x04\x01\x1cwasi-repl:host/command@0.2.0\x05\0\x04\x01\"wasi-repl:host/c\
+ommand-world@0.2.0\x04\0\x0b\x13\x01\0\x0dcommand-world\x03\0\0\0G\x09producers\
+\x01\x0cprocessed-by\x02\x0dwit-component\x070.201.0\x10wit-bindgen-rust\x060.21\
+.0";
 
 #[inline(never)]
 #[doc(hidden)]
@@ -1,14 +1,21 @@
 #[allow(warnings)]
 mod bindings;
 
-use bindings::Guest;
+use bindings::exports::wasi_repl::host::command::{Guest, Outcome};
 
 struct Component {}
 
 impl Guest for Component {
-    fn eval(expr: Vec<String>) -> String {
-        expr.join("")
+    fn eval(args: Vec<String>) -> Outcome {
+        Outcome {
+            output: Ok(args.join("")),
+            status: 0,
+            stderr: None,
+        }
     }
 }
 
-bindings::export!(Component with_types_in bindings);
\ No newline at end of file
+// The export symbols have an `@` in them, which native linkers reject,
+// so only export them when building the component.
+#[cfg(target_arch = "wasm32")]
+bindings::export!(Component with_types_in bindings);
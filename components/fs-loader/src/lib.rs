@@ -1,13 +1,13 @@
 #[allow(warnings)]
 mod bindings;
 
-use bindings::Guest;
+use bindings::exports::wasi_repl::host::loader::Guest;
 
 struct Component {}
 
 impl Guest for Component {
-    fn load(path: String) -> Result<Vec<u8>, String> {
-        std::fs::read(path).or_else(|e| Result::Err(e.to_string()))
+    fn load(cmd: String) -> Result<Vec<u8>, String> {
+        std::fs::read(cmd).map_err(|e| e.to_string())
     }
     fn ls() -> Option<String> {
         let dir = match std::env::current_dir() {
@@ -28,4 +28,7 @@ impl Guest for Component {
     }
 }
 
+// The export symbols have an `@` in them, which native linkers reject,
+// so only export them when building the component.
+#[cfg(target_arch = "wasm32")]
 bindings::export!(Component with_types_in bindings);
@@ -106,6 +106,133 @@ pub mod exports {
             }
         }
     }
+    pub mod wasi_repl {
+        pub mod host {
+            #[allow(clippy::all)]
+            pub mod loader {
+                #[used]
+                #[doc(hidden)]
+                #[cfg(target_arch = "wasm32")]
+                static __FORCE_SECTION_REF: fn() =
+                    super::super::super::super::__link_custom_section_describing_imports;
+                use super::super::super::super::_rt;
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn _export_load_cabi<T: Guest>(arg0: *mut u8, arg1: usize) -> *mut u8 {
+                    let len0 = arg1;
+                    let bytes0 = _rt::Vec::from_raw_parts(arg0.cast(), len0, len0);
+                    let result1 = T::load(_rt::string_lift(bytes0));
+                    let ptr2 = _RET_AREA.0.as_mut_ptr().cast::<u8>();
+                    match result1 {
+                        Ok(e) => {
+                            *ptr2.add(0).cast::<u8>() = (0i32) as u8;
+                            let vec3 = (e).into_boxed_slice();
+                            let ptr3 = vec3.as_ptr().cast::<u8>();
+                            let len3 = vec3.len();
+                            ::core::mem::forget(vec3);
+                            *ptr2.add(8).cast::<usize>() = len3;
+                            *ptr2.add(4).cast::<*mut u8>() = ptr3.cast_mut();
+                        }
+                        Err(e) => {
+                            *ptr2.add(0).cast::<u8>() = (1i32) as u8;
+                            let vec4 = (e.into_bytes()).into_boxed_slice();
+                            let ptr4 = vec4.as_ptr().cast::<u8>();
+                            let len4 = vec4.len();
+                            ::core::mem::forget(vec4);
+                            *ptr2.add(8).cast::<usize>() = len4;
+                            *ptr2.add(4).cast::<*mut u8>() = ptr4.cast_mut();
+                        }
+                    };
+                    ptr2
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn __post_return_load<T: Guest>(arg0: *mut u8) {
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => {
+                            let l1 = *arg0.add(4).cast::<*mut u8>();
+                            let l2 = *arg0.add(8).cast::<usize>();
+                            let base3 = l1;
+                            let len3 = l2;
+                            _rt::cabi_dealloc(base3, len3 * 1, 1);
+                        }
+                        _ => {
+                            let l4 = *arg0.add(4).cast::<*mut u8>();
+                            let l5 = *arg0.add(8).cast::<usize>();
+                            _rt::cabi_dealloc(l4, l5, 1);
+                        }
+                    }
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn _export_ls_cabi<T: Guest>() -> *mut u8 {
+                    let result0 = T::ls();
+                    let ptr1 = _RET_AREA.0.as_mut_ptr().cast::<u8>();
+                    match result0 {
+                        Some(e) => {
+                            *ptr1.add(0).cast::<u8>() = (1i32) as u8;
+                            let vec2 = (e.into_bytes()).into_boxed_slice();
+                            let ptr2 = vec2.as_ptr().cast::<u8>();
+                            let len2 = vec2.len();
+                            ::core::mem::forget(vec2);
+                            *ptr1.add(8).cast::<usize>() = len2;
+                            *ptr1.add(4).cast::<*mut u8>() = ptr2.cast_mut();
+                        }
+                        None => {
+                            *ptr1.add(0).cast::<u8>() = (0i32) as u8;
+                        }
+                    };
+                    ptr1
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn __post_return_ls<T: Guest>(arg0: *mut u8) {
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => (),
+                        _ => {
+                            let l1 = *arg0.add(4).cast::<*mut u8>();
+                            let l2 = *arg0.add(8).cast::<usize>();
+                            _rt::cabi_dealloc(l1, l2, 1);
+                        }
+                    }
+                }
+                pub trait Guest {
+                    fn load(cmd: _rt::String) -> Result<_rt::Vec<u8>, _rt::String>;
+                    fn ls() -> Option<_rt::String>;
+                }
+                #[doc(hidden)]
+
+macro_rules! __export_wasi_repl_host_loader_0_2_0_cabi{
+      ($ty:ident with_types_in $($path_to_types:tt)*) => (const _: () = {
+
+        #[export_name = "wasi-repl:host/loader@0.2.0#load"]
+        unsafe extern "C" fn export_load(arg0: *mut u8,arg1: usize,) -> *mut u8 {
+          $($path_to_types)*::_export_load_cabi::<$ty>(arg0, arg1)
+        }
+        #[export_name = "cabi_post_wasi-repl:host/loader@0.2.0#load"]
+        unsafe extern "C" fn _post_return_load(arg0: *mut u8,) {
+          $($path_to_types)*::__post_return_load::<$ty>(arg0)
+        }
+        #[export_name = "wasi-repl:host/loader@0.2.0#ls"]
+        unsafe extern "C" fn export_ls() -> *mut u8 {
+          $($path_to_types)*::_export_ls_cabi::<$ty>()
+        }
+        #[export_name = "cabi_post_wasi-repl:host/loader@0.2.0#ls"]
+        unsafe extern "C" fn _post_return_ls(arg0: *mut u8,) {
+          $($path_to_types)*::__post_return_ls::<$ty>(arg0)
+        }
+      };);
+    }
+                #[doc(hidden)]
+                pub(crate) use __export_wasi_repl_host_loader_0_2_0_cabi;
+                #[repr(align(4))]
+                struct _RetArea([::core::mem::MaybeUninit<u8>; 12]);
+                static mut _RET_AREA: _RetArea = _RetArea([::core::mem::MaybeUninit::uninit(); 12]);
+            }
+        }
+    }
 }
 mod _rt {
     pub use alloc_crate::string::String;
@@ -151,6 +278,7 @@ macro_rules! __export_ipfs_impl {
   ($ty:ident) => (self::export!($ty with_types_in self););
   ($ty:ident with_types_in $($path_to_types_root:tt)*) => (
   $($path_to_types_root)*::exports::component::ipfs::client::__export_component_ipfs_client_cabi!($ty with_types_in $($path_to_types_root)*::exports::component::ipfs::client);
+  $($path_to_types_root)*::exports::wasi_repl::host::loader::__export_wasi_repl_host_loader_0_2_0_cabi!($ty with_types_in $($path_to_types_root)*::exports::wasi_repl::host::loader);
   )
 }
 #[doc(inline)]
@@ -159,13 +287,15 @@ pub(crate) use __export_ipfs_impl as export;
 #[cfg(target_arch = "wasm32")]
 #[link_section = "component-type:wit-bindgen:0.21.0:ipfs:encoded world"]
 #[doc(hidden)]
-pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; 244] = *b"\
-\0asm\x0d\0\x01\0\0\x19\x16wit-component-encoding\x04\0\x07z\x01A\x02\x01A\x02\x01\
-B\x09\x01s\x04\0\x04data\x03\0\0\x01s\x04\0\x03cid\x03\0\x02\x01@\x01\x04data\x01\
-\0\x03\x04\0\x03put\x01\x04\x01k\x01\x01@\x01\x03cid\x03\0\x05\x04\0\x03get\x01\x06\
-\x04\x01\x15component:ipfs/client\x05\0\x04\x01\x13component:ipfs/ipfs\x04\0\x0b\
-\x0a\x01\0\x04ipfs\x03\0\0\0G\x09producers\x01\x0cprocessed-by\x02\x0dwit-compon\
-ent\x070.201.0\x10wit-bindgen-rust\x060.21.0";
+pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; 323] = *b"\
+\0asm\x0d\0\x01\0\0\x19\x16wit-component-encoding\x04\0\x07\xc8\x01\x01A\x02\x01\
+A\x04\x01B\x09\x01s\x04\0\x04data\x03\0\0\x01s\x04\0\x03cid\x03\0\x02\x01@\x01\
+\x04data\x01\0\x03\x04\0\x03put\x01\x04\x01k\x01\x01@\x01\x03cid\x03\0\x05\x04\0\
+\x03get\x01\x06\x04\x01\x15component:ipfs/client\x05\0\x01B\x07\x01p}\x01j\x01\0\
+\x01s\x01@\x01\x03cmds\0\x01\x04\0\x04load\x01\x02\x01ks\x01@\0\0\x03\x04\0\x02l\
+s\x01\x04\x04\x01\x1bwasi-repl:host/loader@0.2.0\x05\x01\x04\x01\x13component:ip\
+fs/ipfs\x04\0\x0b\x0a\x01\0\x04ipfs\x03\0\0\0G\x09producers\x01\x0cprocessed-by\
+\x02\x0dwit-component\x070.201.0\x10wit-bindgen-rust\x060.21.0";
 
 #[inline(never)]
 #[doc(hidden)]
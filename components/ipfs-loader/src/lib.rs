@@ -2,6 +2,7 @@
 mod bindings;
 
 use bindings::exports::component::ipfs::client::{Data, Cid, Guest};
+use bindings::exports::wasi_repl::host::loader;
 
 struct Component;
 
@@ -14,4 +15,16 @@ impl Guest for Component {
     }
 }
 
+impl loader::Guest for Component {
+    fn load(cmd: String) -> Result<Vec<u8>, String> {
+        Err(format!("{cmd}: fetching commands from IPFS isn't implemented yet"))
+    }
+    fn ls() -> Option<String> {
+        Some("listing IPFS isn't implemented yet".into())
+    }
+}
+
+// The export symbols have an `@` in them, which native linkers reject,
+// so only export them when building the component.
+#[cfg(target_arch = "wasm32")]
 bindings::export!(Component with_types_in bindings);
@@ -0,0 +1,28 @@
+// Host bindings for the worlds in the `wasi-repl:host` package. They
+// type-check a component's exports when it is instantiated, so one built
+// against a different contract fails with an error saying what doesn't
+// match, rather than a panic or a trap when it's called.
+
+/// What loaders implement.
+pub mod loader {
+    wasmtime::component::bindgen!({
+        path: "../wit",
+        world: "loader-world",
+    });
+}
+
+/// What commands implement, besides `wasi:cli/run`.
+pub mod command {
+    wasmtime::component::bindgen!({
+        path: "../wit",
+        world: "command-world",
+    });
+}
+
+/// What commands that work on structured values implement.
+pub mod structured {
+    wasmtime::component::bindgen!({
+        path: "../wit",
+        world: "structured-world",
+    });
+}
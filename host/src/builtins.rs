@@ -0,0 +1,298 @@
+use crate::{cache, parse, Evaluator};
+
+/// A command implemented by the host. Builtins are looked up before any
+/// loader is asked for a component.
+pub struct Builtin {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub about: &'static str,
+    run: fn(&mut Evaluator, Vec<String>) -> anyhow::Result<String>,
+}
+
+impl Builtin {
+    pub fn run(&self, evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+        (self.run)(evaluator, args)
+    }
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "help",
+        usage: "help",
+        about: "list builtin commands",
+        run: help,
+    },
+    Builtin {
+        name: "ls",
+        usage: "ls",
+        about: "list the commands each loader can see",
+        run: ls,
+    },
+    Builtin {
+        name: "which",
+        usage: "which NAME...",
+        about: "show how each name resolves",
+        run: which,
+    },
+    Builtin {
+        name: "policy",
+        usage: "policy [NAME...]",
+        about: "show what commands may access",
+        run: policy,
+    },
+    Builtin {
+        name: "cache",
+        usage: "cache [disk | prune [--all]]",
+        about: "list or prune compiled components",
+        run: cache,
+    },
+    Builtin {
+        name: "fuel",
+        usage: "fuel",
+        about: "show how much fuel the last command used",
+        run: fuel,
+    },
+    Builtin {
+        name: "reload",
+        usage: "reload [NAME...]",
+        about: "evict components from the cache, or all of them",
+        run: reload,
+    },
+    Builtin {
+        name: "clear",
+        usage: "clear",
+        about: "clear the screen",
+        run: clear,
+    },
+    Builtin {
+        name: "call",
+        usage: "call CMD EXPORT [ARG...]",
+        about: "call any exported function, with WAVE arguments",
+        run: call,
+    },
+    Builtin {
+        name: "set",
+        usage: "set NAME VALUE",
+        about: "set a variable",
+        run: set,
+    },
+    Builtin {
+        name: "unset",
+        usage: "unset NAME...",
+        about: "remove variables",
+        run: unset,
+    },
+    Builtin {
+        name: "vars",
+        usage: "vars",
+        about: "list variables",
+        run: vars,
+    },
+    Builtin {
+        name: "exit",
+        usage: "exit [STATUS]",
+        about: "leave the REPL",
+        run: exit,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// How to use `builtin`, for error messages.
+pub fn usage(builtin: &str) -> &str {
+    find(builtin).map_or(builtin, |b| b.usage)
+}
+
+fn no_args(builtin: &str, args: &[String]) -> anyhow::Result<()> {
+    if !args.is_empty() {
+        anyhow::bail!("usage: {}", usage(builtin));
+    }
+    Ok(())
+}
+
+fn help(_: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    no_args("help", &args)?;
+    let width = BUILTINS.iter().map(|b| b.usage.len()).max().unwrap_or(0);
+    let lines = BUILTINS
+        .iter()
+        .map(|b| format!("{:width$}  {}", b.usage, b.about));
+    Ok(lines.collect::<Vec<_>>().join("\n"))
+}
+
+fn ls(evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    no_args("ls", &args)?;
+    let listings = evaluator.loaders.ls();
+    if let [(_, listing)] = listings.as_slice() {
+        return match listing {
+            Ok(listing) => Ok(listing.trim_end().to_string()),
+            Err(e) => anyhow::bail!("{e:#}"),
+        };
+    }
+    let sections = listings.into_iter().map(|(loader, listing)| match listing {
+        Ok(listing) => format!("{loader}:\n{}", listing.trim_end()),
+        Err(e) => format!("{loader}: {e:#}"),
+    });
+    Ok(sections.collect::<Vec<_>>().join("\n\n"))
+}
+
+fn which(evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    if args.is_empty() {
+        anyhow::bail!("usage: {}", usage("which"));
+    }
+    let mut lines = Vec::new();
+    for name in args {
+        if find(&name).is_some() {
+            lines.push(format!("{name}: builtin"));
+        } else if let Some(cached) = evaluator.cache.get(&name) {
+            lines.push(format!("{name}: {} (cached)", cached.source));
+        } else {
+            let loaded = evaluator.loaders.load(&name)?;
+            lines.push(format!("{name}: {}", loaded.source()));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+fn policy(evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    let indent = |text: String| text.replace('\n', "\n  ");
+    if args.is_empty() {
+        let source = match &evaluator.policy.path {
+            Some(path) => path.display().to_string(),
+            None => "no policy file".to_string(),
+        };
+        let grants = evaluator.policy.default_grants().to_string();
+        return Ok(format!("{source}\ndefault:\n  {}", indent(grants)));
+    }
+    let mut sections = Vec::new();
+    for name in args {
+        let loaded = evaluator.loaders.load(&name)?;
+        let digest = cache::digest(&loaded.bytecode);
+        let (grants, entry) = evaluator.policy.for_command(&name, &digest);
+        sections.push(format!("{name}: {entry}\n  {}", indent(grants.to_string())));
+    }
+    Ok(sections.join("\n"))
+}
+
+fn cache(evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let disk_cache = || {
+        evaluator
+            .disk_cache
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("the disk cache is disabled"))
+    };
+    match args.as_slice() {
+        [] => {}
+        ["disk"] => {
+            let disk_cache = disk_cache()?;
+            let mut lines = vec![disk_cache.root().display().to_string()];
+            for usage in disk_cache.usage()? {
+                lines.push(format!(
+                    "  {}: {} entries, {} KiB{}",
+                    usage.engine_key,
+                    usage.entries,
+                    usage.bytes.div_ceil(1024),
+                    if usage.current { " (current)" } else { "" }
+                ));
+            }
+            return Ok(lines.join("\n"));
+        }
+        ["prune"] | ["prune", "--all"] => {
+            let freed = disk_cache()?.prune(args.len() == 2)?;
+            return Ok(format!("freed {} KiB", freed.div_ceil(1024)));
+        }
+        _ => anyhow::bail!("usage: {}", usage("cache")),
+    }
+    let lines = evaluator
+        .cache
+        .iter()
+        .map(|(name, cached)| format!("{name}: {} ({})", cached.source, cached.short_digest()));
+    Ok(lines.collect::<Vec<_>>().join("\n"))
+}
+
+fn fuel(evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    no_args("fuel", &args)?;
+    if !evaluator.metered {
+        anyhow::bail!("fuel is not being counted; set a fuel budget or pass --consume-fuel");
+    }
+    match &evaluator.last_fuel {
+        Some((cmd, used)) => Ok(format!("{cmd}: {used}")),
+        None => anyhow::bail!("no command has run yet"),
+    }
+}
+
+fn reload(evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    if args.is_empty() {
+        evaluator.cache.clear();
+        evaluator.names.forget_listing();
+    }
+    for name in args {
+        if evaluator.cache.remove(&name).is_none() {
+            anyhow::bail!("{name} is not cached");
+        }
+    }
+    Ok(String::new())
+}
+
+fn clear(_: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    no_args("clear", &args)?;
+    print!("\x1b[2J\x1b[H");
+    Ok(String::new())
+}
+
+fn call(evaluator: &mut Evaluator, mut args: Vec<String>) -> anyhow::Result<String> {
+    if args.is_empty() {
+        anyhow::bail!("usage: {}", usage("call"));
+    }
+    let cmd = args.remove(0);
+    evaluator.call(cmd, args)
+}
+
+fn set(evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    let [name, value] =
+        <[String; 2]>::try_from(args).map_err(|_| anyhow::anyhow!("usage: {}", usage("set")))?;
+    if !parse::is_name(&name) {
+        anyhow::bail!("bad variable name `{name}`");
+    }
+    evaluator.vars.insert(name, value);
+    Ok(String::new())
+}
+
+fn unset(evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    if args.is_empty() {
+        anyhow::bail!("usage: {}", usage("unset"));
+    }
+    for name in args {
+        evaluator.vars.remove(&name);
+    }
+    Ok(String::new())
+}
+
+fn vars(evaluator: &mut Evaluator, args: Vec<String>) -> anyhow::Result<String> {
+    no_args("vars", &args)?;
+    let lines = evaluator
+        .vars
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"));
+    Ok(lines.collect::<Vec<_>>().join("\n"))
+}
+
+/// The status `exit` was asked to leave with, if any.
+pub fn exit_status(args: &[String]) -> anyhow::Result<Option<u8>> {
+    match args {
+        [] => Ok(None),
+        [status] => match status.parse() {
+            Ok(status) => Ok(Some(status)),
+            Err(_) => anyhow::bail!("exit: `{status}` is not a status from 0 to 255"),
+        },
+        _ => anyhow::bail!("usage: {}", usage("exit")),
+    }
+}
+
+/// Only reached when `exit` is part of a pipeline; on its own it is
+/// handled by the parser.
+fn exit(_: &mut Evaluator, _: Vec<String>) -> anyhow::Result<String> {
+    anyhow::bail!("`exit` must be used on its own")
+}
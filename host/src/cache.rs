@@ -0,0 +1,193 @@
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+
+const EXTENSION: &str = "cwasm";
+
+pub fn digest(bytecode: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytecode).into()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Feeds what is hashed to SHA-256, which unlike std's hashers gives the
+/// same result from one Rust release to the next.
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        unreachable!("the digest is taken with `finalize`")
+    }
+}
+
+/// Compiled components on disk, under `root/<engine>/<digest>.cwasm`.
+/// `<engine>` hashes everything that makes compiled code incompatible
+/// (wasmtime version, target and engine settings), so changing any of
+/// them starts a fresh directory instead of overwriting entries.
+pub struct DiskCache {
+    root: PathBuf,
+    engine_key: String,
+}
+
+/// Entries compiled for one engine configuration.
+pub struct Usage {
+    pub engine_key: String,
+    pub current: bool,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+impl DiskCache {
+    pub fn new(root: PathBuf, engine: &wasmtime::Engine) -> Self {
+        let mut hasher = Sha256Hasher(Sha256::new());
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        Self {
+            root,
+            engine_key: hex(&hasher.0.finalize()[..8]),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, digest: &[u8; 32]) -> PathBuf {
+        self.root
+            .join(&self.engine_key)
+            .join(hex(digest))
+            .with_extension(EXTENSION)
+    }
+
+    /// Loads a previously compiled component, or `None` on a miss. Entries
+    /// that fail to deserialize are treated as misses.
+    fn get(
+        &self,
+        engine: &wasmtime::Engine,
+        digest: &[u8; 32],
+    ) -> Option<wasmtime::component::Component> {
+        let path = self.path(digest);
+        if !path.is_file() {
+            return None;
+        }
+        // SAFETY: entries are only ever written by `put`, from
+        // `Component::serialize` output for this same engine configuration.
+        // Anyone able to replace them can already run code as this user.
+        unsafe { wasmtime::component::Component::deserialize_file(engine, &path) }.ok()
+    }
+
+    fn put(
+        &self,
+        digest: &[u8; 32],
+        component: &wasmtime::component::Component,
+    ) -> anyhow::Result<()> {
+        let path = self.path(digest);
+        let dir = path.parent().expect("entries live in an engine directory");
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+        // Write to a temporary file first so that a concurrent REPL never
+        // sees a partial entry.
+        let tmp = path.with_extension(format!("{EXTENSION}.{}", std::process::id()));
+        std::fs::write(&tmp, component.serialize()?)
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Entry counts and sizes for every engine configuration in the cache.
+    pub fn usage(&self) -> anyhow::Result<Vec<Usage>> {
+        let mut usage = Vec::new();
+        for dir in self.engine_dirs()? {
+            let mut entries = 0;
+            let mut bytes = 0;
+            for entry in std::fs::read_dir(&dir)? {
+                let metadata = entry?.metadata()?;
+                if metadata.is_file() {
+                    entries += 1;
+                    bytes += metadata.len();
+                }
+            }
+            let engine_key = dir
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            usage.push(Usage {
+                current: engine_key == self.engine_key,
+                engine_key,
+                entries,
+                bytes,
+            });
+        }
+        Ok(usage)
+    }
+
+    /// Removes entries compiled for other engine configurations, which can
+    /// never be loaded again, or every entry if `all` is set. Returns the
+    /// number of bytes freed.
+    pub fn prune(&self, all: bool) -> anyhow::Result<u64> {
+        let mut freed = 0;
+        for usage in self.usage()? {
+            if all || !usage.current {
+                let dir = self.root.join(&usage.engine_key);
+                std::fs::remove_dir_all(&dir)
+                    .with_context(|| format!("failed to remove {}", dir.display()))?;
+                freed += usage.bytes;
+            }
+        }
+        Ok(freed)
+    }
+
+    /// Subdirectories of the root named like an engine key. Anything else
+    /// in the root isn't ours and is left alone.
+    fn engine_dirs(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to read {}", self.root.display()))
+            }
+        };
+        let mut dirs = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let is_key = name.len() == 16
+                && name
+                    .to_string_lossy()
+                    .chars()
+                    .all(|c| c.is_ascii_hexdigit());
+            if is_key && entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+        dirs.sort();
+        Ok(dirs)
+    }
+}
+
+/// Compiles `bytecode`, whose digest is `digest`, going through the disk
+/// cache when there is one. Failing to write the cache is only a warning.
+pub fn compile(
+    engine: &wasmtime::Engine,
+    cache: Option<&DiskCache>,
+    bytecode: &[u8],
+    digest: &[u8; 32],
+) -> anyhow::Result<wasmtime::component::Component> {
+    if let Some(component) = cache.and_then(|cache| cache.get(engine, digest)) {
+        return Ok(component);
+    }
+    let component = wasmtime::component::Component::from_binary(engine, bytecode)?;
+    if let Some(cache) = cache {
+        if let Err(e) = cache.put(digest, &component) {
+            eprintln!("warning: {e:#}");
+        }
+    }
+    Ok(component)
+}
@@ -0,0 +1,141 @@
+//! Calling any function a component exports, whatever its signature, for
+//! the `call` builtin.
+
+use wasmtime::component::{Func, Instance, Val};
+use wasmtime::Store;
+
+use crate::wave;
+
+/// A function or instance a component exports at its top level.
+pub struct Export {
+    pub name: String,
+    pub instance: bool,
+}
+
+/// The functions and instances a component exports, read from its
+/// bytecode because wasmtime can only look exports up by name.
+pub fn exports(bytecode: &[u8]) -> anyhow::Result<Vec<Export>> {
+    use wasmparser::{ComponentExternalKind, Parser, Payload};
+
+    let mut exports = Vec::new();
+    // Nested modules and components have exports of their own.
+    let mut depth = 0;
+    for payload in Parser::new(0).parse_all(bytecode) {
+        match payload? {
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => depth += 1,
+            Payload::End(_) => depth -= 1,
+            Payload::ComponentExportSection(section) if depth == 0 => {
+                for export in section {
+                    let export = export?;
+                    let instance = match export.kind {
+                        ComponentExternalKind::Func => false,
+                        ComponentExternalKind::Instance => true,
+                        _ => continue,
+                    };
+                    exports.push(Export {
+                        name: export.name.0.to_string(),
+                        instance,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(exports)
+}
+
+/// The name of an interface without its package or version, as in
+/// `client` for `component:ipfs/client@0.1.0`.
+fn short_name(name: &str) -> &str {
+    let name = name.rsplit_once('/').map_or(name, |(_, name)| name);
+    name.split_once('@').map_or(name, |(name, _)| name)
+}
+
+/// Finds the function `path` names: `INSTANCE#FUNC` or `INSTANCE.FUNC`
+/// for one in an exported instance, which may be given by its short
+/// name, or `FUNC` for one the component exports itself.
+pub fn find<T>(
+    store: &mut Store<T>,
+    instance: &Instance,
+    exports: &[Export],
+    path: &str,
+) -> anyhow::Result<Func> {
+    let split = path.split_once('#').or_else(|| path.rsplit_once('.'));
+    if let Some((name, func)) = split {
+        let matches: Vec<&Export> = exports
+            .iter()
+            .filter(|export| {
+                export.instance && (export.name == name || short_name(&export.name) == name)
+            })
+            .collect();
+        match matches.as_slice() {
+            [export] => {
+                let mut root = instance.exports(&mut *store);
+                let mut exported = root
+                    .instance(&export.name)
+                    .ok_or_else(|| anyhow::anyhow!("no instance `{}`", export.name))?;
+                return exported
+                    .func(func)
+                    .ok_or_else(|| anyhow::anyhow!("`{}` has no function `{func}`", export.name));
+            }
+            [] if path.contains('#') => {
+                anyhow::bail!(
+                    "no instance `{name}`; the component exports {}",
+                    list(exports)
+                )
+            }
+            // A dot may be part of a version instead.
+            [] => {}
+            _ => anyhow::bail!("`{name}` could be any of {}", list(matches.iter().copied())),
+        }
+    }
+    let func = instance.exports(&mut *store).root().func(path);
+    func.ok_or_else(|| {
+        anyhow::anyhow!(
+            "no export `{path}`; the component exports {}",
+            list(exports)
+        )
+    })
+}
+
+/// The names of `exports`, for error messages.
+fn list<'a>(exports: impl IntoIterator<Item = &'a Export>) -> String {
+    let names: Vec<_> = exports
+        .into_iter()
+        .map(|export| match export.instance {
+            true => format!("`{}` (instance)", export.name),
+            false => format!("`{}`", export.name),
+        })
+        .collect();
+    match names.is_empty() {
+        true => "nothing".to_string(),
+        false => names.join(", "),
+    }
+}
+
+/// Calls `func` with `args`, each parsed in WAVE syntax as the type of
+/// its parameter, and returns its results.
+pub fn call<T>(store: &mut Store<T>, func: &Func, args: &[String]) -> anyhow::Result<Vec<Val>> {
+    let types = func.params(&*store);
+    if args.len() != types.len() {
+        let types: Vec<_> = types.iter().map(wave::describe).collect();
+        anyhow::bail!(
+            "expected {} argument{} ({}), got {}",
+            types.len(),
+            if types.len() == 1 { "" } else { "s" },
+            types.join(", "),
+            args.len()
+        );
+    }
+    let params = types
+        .iter()
+        .zip(args)
+        .enumerate()
+        .map(|(i, (ty, arg))| wave::parse_arg(ty, i, arg))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    // Placeholders, which `call` overwrites.
+    let mut results = vec![Val::Bool(false); func.results(&*store).len()];
+    func.call(&mut *store, &params, &mut results)?;
+    func.post_return(&mut *store)?;
+    Ok(results)
+}
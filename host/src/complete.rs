@@ -0,0 +1,127 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+
+use crate::builtins::BUILTINS;
+use crate::grants::Grants;
+use crate::loader::LoaderChain;
+
+/// Command names to offer at the prompt. The loaders' `ls` listings are
+/// fetched the first time they're needed and kept until
+/// [`Names::forget_listing`], so pressing Tab doesn't instantiate every
+/// loader each time.
+pub struct Names {
+    loaders: Rc<LoaderChain>,
+    listing: RefCell<Option<Vec<String>>>,
+    /// Commands in the evaluator's cache.
+    cached: RefCell<Vec<String>>,
+}
+
+impl Names {
+    pub fn new(loaders: Rc<LoaderChain>) -> Self {
+        Self {
+            loaders,
+            listing: RefCell::new(None),
+            cached: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn set_cached(&self, names: impl IntoIterator<Item = String>) {
+        *self.cached.borrow_mut() = names.into_iter().collect();
+    }
+
+    /// Makes the next completion ask the loaders again.
+    pub fn forget_listing(&self) {
+        self.listing.borrow_mut().take();
+    }
+
+    /// Every known name starting with `prefix`, sorted.
+    fn matching(&self, prefix: &str) -> Vec<String> {
+        let mut listing = self.listing.borrow_mut();
+        let listing = listing.get_or_insert_with(|| {
+            let mut names = Vec::new();
+            // Loaders that can't list their commands just aren't offered.
+            for (_, listing) in self.loaders.ls() {
+                if let Ok(listing) = listing {
+                    let lines = listing.lines().map(str::trim);
+                    names.extend(lines.filter(|l| !l.is_empty()).map(str::to_string));
+                }
+            }
+            names
+        });
+        let builtins = BUILTINS.iter().map(|b| b.name.to_string());
+        let cached = self.cached.borrow();
+        let names: BTreeSet<String> = builtins
+            .chain(cached.iter().cloned())
+            .chain(listing.iter().cloned())
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.into_iter().collect()
+    }
+}
+
+/// Completes command names at the prompt.
+pub struct Helper {
+    names: Rc<Names>,
+}
+
+impl Helper {
+    pub fn new(names: Rc<Names>) -> Self {
+        Self { names }
+    }
+}
+
+/// Whether a word starting at the end of `before` names a command: it is
+/// the first word of a pipeline stage or `$(...)`, or after `&&` or `||`,
+/// grant flags aside.
+fn is_command_position(before: &str) -> bool {
+    let stage = match before.rfind(['|', '(', '&']) {
+        Some(i) => &before[i + 1..],
+        None => before,
+    };
+    let mut words = stage.split_whitespace();
+    while let Some(word) = words.next() {
+        let Some(flag) = word.strip_prefix("--") else {
+            return false;
+        };
+        if !flag.contains('=') && Grants::takes_value(flag) {
+            words.next();
+        }
+    }
+    true
+}
+
+impl Completer for Helper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let start = before
+            .rfind(|c: char| c.is_whitespace() || "|(&".contains(c))
+            .map_or(0, |i| i + 1);
+        if !is_command_position(&before[..start]) {
+            return Ok((start, Vec::new()));
+        }
+        Ok((start, self.names.matching(&before[start..])))
+    }
+}
+
+impl Hinter for Helper {
+    type Hint = String;
+}
+
+impl Highlighter for Helper {}
+
+impl Validator for Helper {}
+
+impl rustyline::Helper for Helper {}
@@ -0,0 +1,451 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use clap::Parser;
+use serde::Deserialize;
+
+const CONFIG_FILE_NAME: &str = "wasi-repl.toml";
+
+/// Command-line arguments. Anything not given here falls back to the
+/// config file, and list arguments replace the file's lists entirely.
+#[derive(Parser, Debug)]
+#[command(name = "wasi-repl", version, about = "A REPL for WASI components")]
+pub struct Args {
+    /// Script to run instead of starting the REPL, or `-` for stdin
+    #[arg(value_name = "SCRIPT")]
+    pub script: Option<PathBuf>,
+
+    /// Run COMMANDS instead of starting the REPL
+    #[arg(short = 'c', value_name = "COMMANDS", conflicts_with = "script")]
+    pub command: Option<String>,
+
+    /// Carry on with a script after an input fails. The exit status still
+    /// reports the failure
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// Config file [default: ./wasi-repl.toml, then the user config dir]
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Loader component that resolves command names to bytecode. Replaces
+    /// the loader chain from the config file
+    #[arg(long, value_name = "PATH")]
+    pub loader: Option<PathBuf>,
+
+    /// Host directory to preopen in the --loader (repeatable)
+    #[arg(long = "preopen", value_name = "HOST[::GUEST]", requires = "loader")]
+    pub preopens: Vec<String>,
+
+    /// Directory the --loader searches for commands, in order (repeatable)
+    /// [default: the guest path of every preopen]
+    #[arg(long = "path", value_name = "DIR", requires = "loader")]
+    pub search_path: Vec<String>,
+
+    /// Cranelift optimization level
+    #[arg(long, value_enum, value_name = "LEVEL")]
+    pub opt_level: Option<OptLevel>,
+
+    /// Generate DWARF debug info for compiled components
+    #[arg(long)]
+    pub debug_info: bool,
+
+    /// Directory for compiled components [default: the user cache dir]
+    #[arg(long, value_name = "PATH")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Compile every component from scratch instead of using the cache dir
+    #[arg(long)]
+    pub no_disk_cache: bool,
+
+    /// File to keep the prompt's history in [default: in the user data dir]
+    #[arg(long, value_name = "PATH")]
+    pub history: Option<PathBuf>,
+
+    /// Don't load or save the prompt's history
+    #[arg(long)]
+    pub no_history: bool,
+
+    /// Policy file that sets what each command may access
+    #[arg(long, value_name = "PATH")]
+    pub policy: Option<PathBuf>,
+
+    /// Stop any command that runs longer than this many seconds
+    #[arg(long, value_name = "SECS")]
+    pub timeout: Option<f64>,
+
+    /// Stop any command that uses more than this much fuel. Implies
+    /// --consume-fuel
+    #[arg(long, value_name = "UNITS")]
+    pub fuel: Option<u64>,
+
+    /// Count the fuel every command uses, with or without a budget
+    #[arg(long)]
+    pub consume_fuel: bool,
+
+    /// Largest any command's linear memory may grow, in 64 KiB pages
+    #[arg(long, value_name = "PAGES")]
+    pub memory_pages: Option<u64>,
+
+    /// Largest any command's tables may grow, in elements
+    #[arg(long, value_name = "ELEMENTS")]
+    pub table_elements: Option<u32>,
+
+    /// Most core instances any command may create
+    #[arg(long, value_name = "COUNT")]
+    pub instances: Option<usize>,
+}
+
+#[derive(clap::ValueEnum, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum OptLevel {
+    None,
+    #[default]
+    Speed,
+    SpeedAndSize,
+}
+
+impl From<OptLevel> for wasmtime::OptLevel {
+    fn from(level: OptLevel) -> Self {
+        match level {
+            OptLevel::None => wasmtime::OptLevel::None,
+            OptLevel::Speed => wasmtime::OptLevel::Speed,
+            OptLevel::SpeedAndSize => wasmtime::OptLevel::SpeedAndSize,
+        }
+    }
+}
+
+/// On-disk layout of `wasi-repl.toml`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct ConfigFile {
+    policy: Option<PathBuf>,
+    /// `false` turns history off.
+    history: Option<HistoryFile>,
+    loaders: Vec<LoaderFile>,
+    engine: EngineFile,
+    limits: LimitsFile,
+    command_limits: BTreeMap<String, LimitsFile>,
+}
+
+/// `history = "PATH"` or `history = false`.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum HistoryFile {
+    Enabled(bool),
+    Path(PathBuf),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct LoaderFile {
+    name: Option<String>,
+    path: PathBuf,
+    #[serde(default)]
+    preopens: Vec<String>,
+    #[serde(default)]
+    search_path: Vec<String>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct EngineFile {
+    opt_level: Option<OptLevel>,
+    debug_info: bool,
+    cache_dir: Option<PathBuf>,
+    disk_cache: Option<bool>,
+    consume_fuel: bool,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct LimitsFile {
+    timeout: Option<f64>,
+    fuel: Option<u64>,
+    memory_pages: Option<u64>,
+    table_elements: Option<u32>,
+    instances: Option<usize>,
+}
+
+/// A host directory made visible to a guest at `guest`.
+#[derive(Clone, Debug)]
+pub struct Preopen {
+    pub host: PathBuf,
+    pub guest: String,
+}
+
+impl Preopen {
+    /// Parses `HOST[::GUEST]`, resolving a relative `HOST` against `base`.
+    pub fn parse(spec: &str, base: &Path) -> Self {
+        let (host, guest) = match spec.split_once("::") {
+            Some((host, guest)) => (host, guest),
+            None => (spec, "/"),
+        };
+        Self {
+            host: base.join(host),
+            guest: guest.to_string(),
+        }
+    }
+
+    /// Opens the host directory and preopens it in `builder`, read-only
+    /// unless `write` is set.
+    pub fn add_to(
+        &self,
+        builder: &mut wasmtime_wasi::WasiCtxBuilder,
+        write: bool,
+    ) -> anyhow::Result<()> {
+        let dir = std::fs::File::open(&self.host)
+            .with_context(|| format!("failed to open {}", self.host.display()))?;
+        let (dir_perms, file_perms) = match write {
+            false => (
+                wasmtime_wasi::DirPerms::READ,
+                wasmtime_wasi::FilePerms::READ,
+            ),
+            true => (
+                wasmtime_wasi::DirPerms::all(),
+                wasmtime_wasi::FilePerms::all(),
+            ),
+        };
+        builder.preopened_dir(
+            wasi_common::sync::Dir::from_std_file(dir),
+            dir_perms,
+            file_perms,
+            &self.guest,
+        );
+        Ok(())
+    }
+}
+
+/// One entry in the loader chain.
+#[derive(Debug)]
+pub struct LoaderConfig {
+    pub name: String,
+    pub path: PathBuf,
+    pub preopens: Vec<Preopen>,
+    pub search_path: Vec<String>,
+}
+
+impl LoaderConfig {
+    /// Resolves relative paths against `base`. The name defaults to the
+    /// component's file stem, and the search path to every preopen.
+    fn new(
+        name: Option<String>,
+        path: PathBuf,
+        preopens: &[String],
+        search_path: Vec<String>,
+        base: &Path,
+    ) -> Self {
+        let path = base.join(path);
+        let name = name.unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string())
+        });
+        let preopens: Vec<Preopen> = preopens.iter().map(|p| Preopen::parse(p, base)).collect();
+        let search_path = if search_path.is_empty() {
+            preopens.iter().map(|p| p.guest.clone()).collect()
+        } else {
+            search_path
+        };
+        Self {
+            name,
+            path,
+            preopens,
+            search_path,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct EngineConfig {
+    pub opt_level: OptLevel,
+    pub debug_info: bool,
+    /// Where compiled components are cached, if anywhere.
+    pub cache_dir: Option<PathBuf>,
+    /// Whether stores count the fuel guest code uses.
+    pub consume_fuel: bool,
+}
+
+impl EngineConfig {
+    pub fn build(&self) -> anyhow::Result<wasmtime::Engine> {
+        let mut config = wasmtime::Config::new();
+        config
+            .wasm_component_model(true)
+            .cranelift_opt_level(self.opt_level.into())
+            .debug_info(self.debug_info)
+            .epoch_interruption(true)
+            .consume_fuel(self.consume_fuel);
+        wasmtime::Engine::new(&config)
+    }
+}
+
+/// What one command invocation is allowed to use. `None` is no limit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    pub timeout: Option<Duration>,
+    pub fuel: Option<u64>,
+    pub memory_pages: Option<u64>,
+    pub table_elements: Option<u32>,
+    pub instances: Option<usize>,
+}
+
+impl Limits {
+    fn new(file: LimitsFile) -> anyhow::Result<Self> {
+        let timeout = match file.timeout {
+            Some(secs) => Some(
+                Duration::try_from_secs_f64(secs)
+                    .map_err(|_| anyhow::anyhow!("bad timeout `{secs}`"))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            timeout,
+            fuel: file.fuel,
+            memory_pages: file.memory_pages,
+            table_elements: file.table_elements,
+            instances: file.instances,
+        })
+    }
+
+    /// These limits, with anything unset taken from `defaults`.
+    fn or(self, defaults: Limits) -> Limits {
+        Limits {
+            timeout: self.timeout.or(defaults.timeout),
+            fuel: self.fuel.or(defaults.fuel),
+            memory_pages: self.memory_pages.or(defaults.memory_pages),
+            table_elements: self.table_elements.or(defaults.table_elements),
+            instances: self.instances.or(defaults.instances),
+        }
+    }
+}
+
+/// Limits for every command, and overrides for commands by name.
+#[derive(Clone, Debug, Default)]
+pub struct LimitsConfig {
+    pub defaults: Limits,
+    pub commands: BTreeMap<String, Limits>,
+}
+
+impl LimitsConfig {
+    pub fn for_command(&self, name: &str) -> Limits {
+        match self.commands.get(name) {
+            Some(limits) => limits.or(self.defaults),
+            None => self.defaults,
+        }
+    }
+
+    fn has_fuel(&self) -> bool {
+        self.defaults.fuel.is_some() || self.commands.values().any(|l| l.fuel.is_some())
+    }
+}
+
+/// Fully resolved settings, with every path made absolute or relative to
+/// the current directory.
+#[derive(Debug)]
+pub struct Config {
+    pub loaders: Vec<LoaderConfig>,
+    pub policy: Option<PathBuf>,
+    /// Where the prompt's history is kept, if anywhere.
+    pub history: Option<PathBuf>,
+    pub engine: EngineConfig,
+    pub limits: LimitsConfig,
+}
+
+impl Config {
+    pub fn load(args: Args) -> anyhow::Result<Self> {
+        let (file, file_path, base) = match args.config.or_else(default_config_path) {
+            Some(path) => {
+                let text = std::fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                let file: ConfigFile = toml::from_str(&text)
+                    .with_context(|| format!("failed to parse {}", path.display()))?;
+                let base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                (file, Some(path), base)
+            }
+            None => (ConfigFile::default(), None, PathBuf::new()),
+        };
+
+        let loaders = match args.loader {
+            Some(path) => vec![LoaderConfig::new(
+                None,
+                path,
+                &args.preopens,
+                args.search_path,
+                Path::new(""),
+            )],
+            None => file
+                .loaders
+                .into_iter()
+                .map(|l| LoaderConfig::new(l.name, l.path, &l.preopens, l.search_path, &base))
+                .collect(),
+        };
+        if loaders.is_empty() {
+            match file_path {
+                Some(path) => anyhow::bail!("no loaders set in {}", path.display()),
+                None => anyhow::bail!(
+                    "no loader configured; pass --loader or create {CONFIG_FILE_NAME}"
+                ),
+            }
+        }
+
+        let cache_dir = if args.no_disk_cache || file.engine.disk_cache == Some(false) {
+            None
+        } else {
+            args.cache_dir
+                .or_else(|| file.engine.cache_dir.map(|dir| base.join(dir)))
+                .or_else(|| Some(dirs::cache_dir()?.join("wasi-repl")))
+        };
+
+        let history = match (args.history, file.history) {
+            _ if args.no_history => None,
+            (Some(path), _) => Some(path),
+            (None, Some(HistoryFile::Path(path))) => Some(base.join(path)),
+            (None, Some(HistoryFile::Enabled(false))) => None,
+            (None, Some(HistoryFile::Enabled(true)) | None) => {
+                dirs::data_dir().map(|dir| dir.join("wasi-repl").join("history"))
+            }
+        };
+
+        let limits = LimitsConfig {
+            defaults: Limits::new(LimitsFile {
+                timeout: args.timeout.or(file.limits.timeout),
+                fuel: args.fuel.or(file.limits.fuel),
+                memory_pages: args.memory_pages.or(file.limits.memory_pages),
+                table_elements: args.table_elements.or(file.limits.table_elements),
+                instances: args.instances.or(file.limits.instances),
+            })?,
+            commands: file
+                .command_limits
+                .into_iter()
+                .map(|(name, limits)| Ok((name, Limits::new(limits)?)))
+                .collect::<anyhow::Result<_>>()?,
+        };
+
+        Ok(Self {
+            loaders,
+            policy: args
+                .policy
+                .or_else(|| file.policy.map(|path| base.join(path))),
+            history,
+            engine: EngineConfig {
+                opt_level: args.opt_level.or(file.engine.opt_level).unwrap_or_default(),
+                debug_info: args.debug_info || file.engine.debug_info,
+                cache_dir,
+                consume_fuel: args.consume_fuel || file.engine.consume_fuel || limits.has_fuel(),
+            },
+            limits,
+        })
+    }
+}
+
+/// `./wasi-repl.toml` if present, else `<config dir>/wasi-repl/wasi-repl.toml`.
+fn default_config_path() -> Option<PathBuf> {
+    let local = PathBuf::from(CONFIG_FILE_NAME);
+    if local.is_file() {
+        return Some(local);
+    }
+    let global = dirs::config_dir()?.join("wasi-repl").join(CONFIG_FILE_NAME);
+    global.is_file().then_some(global)
+}
@@ -0,0 +1,195 @@
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::Preopen;
+
+/// What one command invocation may access. By default a command gets its
+/// stdio, which is how it talks to the REPL, and nothing else: no
+/// directories, no environment variables, no network, clocks that never
+/// move and randomness that never changes.
+#[derive(Clone, Debug)]
+pub struct Grants {
+    /// Preopened directories, and whether each one is writable.
+    pub dirs: Vec<(Preopen, bool)>,
+    pub env: Vec<(String, String)>,
+    pub stdio: bool,
+    pub clocks: bool,
+    pub random: bool,
+    pub network: bool,
+}
+
+impl Default for Grants {
+    fn default() -> Self {
+        Self {
+            stdio: true,
+            ..Self::none()
+        }
+    }
+}
+
+impl Grants {
+    /// No capabilities at all, not even stdio.
+    pub fn none() -> Self {
+        Self {
+            dirs: Vec::new(),
+            env: Vec::new(),
+            stdio: false,
+            clocks: false,
+            random: false,
+            network: false,
+        }
+    }
+
+    /// Splits grant flags off the front of a command line, returning them
+    /// with the command name and its arguments. Flags are
+    /// `--allow-read HOST[::GUEST]`, `--allow-write HOST[::GUEST]` and
+    /// `--env KEY=VALUE`, each also accepted as `--flag=VALUE`, and
+    /// `--allow-clocks`, `--allow-random` and `--allow-network`.
+    pub fn split(name: String, args: Vec<String>) -> anyhow::Result<(Grants, String, Vec<String>)> {
+        let mut grants = Grants::none();
+        let mut words = std::iter::once(name).chain(args);
+        while let Some(word) = words.next() {
+            let Some(flag) = word.strip_prefix("--") else {
+                return Ok((grants, word, words.collect()));
+            };
+            let (flag, value) = match flag.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (flag, None),
+            };
+            let switch = match flag {
+                "allow-clocks" => Some(&mut grants.clocks),
+                "allow-random" => Some(&mut grants.random),
+                "allow-network" => Some(&mut grants.network),
+                _ => None,
+            };
+            if let Some(switch) = switch {
+                if value.is_some() {
+                    anyhow::bail!("`--{flag}` takes no value");
+                }
+                *switch = true;
+                continue;
+            }
+            let Some(value) = value.or_else(|| words.next()) else {
+                anyhow::bail!("missing value for `--{flag}`");
+            };
+            match flag {
+                "allow-read" | "allow-write" => {
+                    let preopen = Preopen::parse(&value, Path::new(""));
+                    grants.dirs.push((preopen, flag == "allow-write"));
+                }
+                "env" => match value.split_once('=') {
+                    Some((key, value)) => grants.env.push((key.to_string(), value.to_string())),
+                    None => anyhow::bail!("expected `--env KEY=VALUE`, got `{value}`"),
+                },
+                _ => anyhow::bail!("unknown grant `--{flag}`"),
+            }
+        }
+        anyhow::bail!("missing command after grants")
+    }
+
+    /// Whether `--flag` takes a value, as opposed to being a switch.
+    pub fn takes_value(flag: &str) -> bool {
+        matches!(flag, "allow-read" | "allow-write" | "env")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty()
+            && self.env.is_empty()
+            && !(self.stdio || self.clocks || self.random || self.network)
+    }
+
+    /// Adds everything `other` grants to these grants.
+    pub fn extend(&mut self, other: Grants) {
+        self.dirs.extend(other.dirs);
+        self.env.extend(other.env);
+        self.stdio |= other.stdio;
+        self.clocks |= other.clocks;
+        self.random |= other.random;
+        self.network |= other.network;
+    }
+
+    /// Applies everything but stdio, which the caller wires up itself.
+    pub fn add_to(&self, builder: &mut wasmtime_wasi::WasiCtxBuilder) -> anyhow::Result<()> {
+        for (preopen, write) in &self.dirs {
+            preopen.add_to(builder, *write)?;
+        }
+        builder.envs(&self.env);
+        if !self.clocks {
+            builder.wall_clock(FrozenClock).monotonic_clock(FrozenClock);
+        }
+        if !self.random {
+            builder
+                .secure_random(wasmtime_wasi::Deterministic::new(FIXED_RANDOM.to_vec()))
+                .insecure_random(wasmtime_wasi::Deterministic::new(FIXED_RANDOM.to_vec()))
+                .insecure_random_seed(0);
+        }
+        if self.network {
+            builder.inherit_network().allow_ip_name_lookup(true);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Grants {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (preopen, write) in &self.dirs {
+            let access = if *write { "read-write" } else { "read-only" };
+            writeln!(
+                f,
+                "{} at {} ({access})",
+                preopen.host.display(),
+                preopen.guest
+            )?;
+        }
+        for (key, value) in &self.env {
+            writeln!(f, "env {key}={value}")?;
+        }
+        let switches = [
+            ("stdio", self.stdio),
+            ("clocks", self.clocks),
+            ("random", self.random),
+            ("network", self.network),
+        ];
+        let list = |allowed: bool| {
+            let names: Vec<_> = switches
+                .iter()
+                .filter(|(_, on)| *on == allowed)
+                .map(|(name, _)| *name)
+                .collect();
+            names.join(", ")
+        };
+        match (list(true), list(false)) {
+            (allowed, denied) if denied.is_empty() => write!(f, "allow {allowed}"),
+            (allowed, denied) if allowed.is_empty() => write!(f, "deny {denied}"),
+            (allowed, denied) => write!(f, "allow {allowed}; deny {denied}"),
+        }
+    }
+}
+
+/// What a command without the `random` grant gets instead of randomness.
+const FIXED_RANDOM: [u8; 8] = [0x9e, 0x37, 0x79, 0xb9, 0x7f, 0x4a, 0x7c, 0x15];
+
+/// Stands in for the wall and monotonic clocks of a command without the
+/// `clocks` grant. It always reads zero.
+struct FrozenClock;
+
+impl wasmtime_wasi::HostWallClock for FrozenClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+impl wasmtime_wasi::HostMonotonicClock for FrozenClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        0
+    }
+}
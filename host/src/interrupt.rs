@@ -0,0 +1,73 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often the engine's epoch advances, and so how quickly guest code
+/// notices Ctrl-C or a timeout.
+const TICK: Duration = Duration::from_millis(10);
+
+/// Set by the Ctrl-C handler, and cleared when guest code is stopped for
+/// it or the next input starts.
+static CTRL_C: AtomicBool = AtomicBool::new(false);
+
+/// Why guest code was stopped.
+#[derive(Debug)]
+pub enum Interrupted {
+    CtrlC,
+    Timeout(Duration),
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interrupted::CtrlC => write!(f, "interrupted"),
+            Interrupted::Timeout(timeout) => write!(f, "timed out after {timeout:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Starts advancing `engine`'s epoch in the background and installs the
+/// Ctrl-C handler. The engine must have epoch interruption enabled.
+pub fn start(engine: &wasmtime::Engine) -> anyhow::Result<()> {
+    let engine = engine.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK);
+        engine.increment_epoch();
+    });
+    // At the prompt the terminal is in raw mode and rustyline sees Ctrl-C
+    // as a key press, so this only fires while guest code is running.
+    ctrlc::set_handler(|| CTRL_C.store(true, Ordering::SeqCst))?;
+    Ok(())
+}
+
+/// Whether Ctrl-C was pressed and hasn't stopped guest code yet,
+/// clearing it.
+pub fn take() -> bool {
+    CTRL_C.swap(false, Ordering::SeqCst)
+}
+
+/// Forgets a Ctrl-C from before an input, so it can't stop the input.
+pub fn clear() {
+    CTRL_C.store(false, Ordering::SeqCst);
+}
+
+/// Makes guest code in `store` trap with [`Interrupted`] on Ctrl-C, or
+/// once `timeout` has passed. A Ctrl-C pressed earlier in the same input,
+/// while the host was busy, stops it straight away.
+pub fn arm<T>(store: &mut wasmtime::Store<T>, timeout: Option<Duration>) {
+    let start = Instant::now();
+    store.set_epoch_deadline(1);
+    store.epoch_deadline_callback(move |_| {
+        if CTRL_C.swap(false, Ordering::SeqCst) {
+            return Err(Interrupted::CtrlC.into());
+        }
+        match timeout {
+            Some(timeout) if start.elapsed() >= timeout => {
+                Err(Interrupted::Timeout(timeout).into())
+            }
+            _ => Ok(wasmtime::UpdateDeadline::Continue(1)),
+        }
+    });
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+
+use crate::config::Limits;
+use crate::interrupt;
+
+/// Applies `limits` to a fresh store. `metered` must match whether the
+/// engine consumes fuel; metered stores without a budget get as much fuel
+/// as they can hold.
+pub fn arm<T>(
+    store: &mut wasmtime::Store<T>,
+    limits: &Limits,
+    metered: bool,
+) -> anyhow::Result<()> {
+    interrupt::arm(store, limits.timeout);
+    if metered {
+        store.set_fuel(limits.fuel.unwrap_or(u64::MAX))?;
+    }
+    Ok(())
+}
+
+/// A command used up its fuel budget.
+#[derive(Debug)]
+pub struct OutOfFuel {
+    pub cmd: String,
+    pub budget: u64,
+}
+
+impl fmt::Display for OutOfFuel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: out of fuel after {} units", self.cmd, self.budget)
+    }
+}
+
+impl std::error::Error for OutOfFuel {}
+
+/// Size of a linear memory page.
+const PAGE_SIZE: u64 = 64 * 1024;
+
+/// Refuses to let a command's memories or tables grow past its limits.
+/// Growth is refused with an error rather than by failing the grow
+/// instruction, so the command stops with [`LimitExceeded`] instead of
+/// aborting somewhere in its allocator.
+pub struct Limiter {
+    cmd: String,
+    limits: Limits,
+}
+
+impl Limiter {
+    pub fn new(cmd: &str, limits: &Limits) -> Self {
+        Self {
+            cmd: cmd.to_string(),
+            limits: *limits,
+        }
+    }
+}
+
+impl wasmtime::ResourceLimiter for Limiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        match self.limits.memory_pages {
+            Some(pages) if desired as u64 > pages.saturating_mul(PAGE_SIZE) => Err(LimitExceeded {
+                cmd: self.cmd.clone(),
+                limit: Limit::MemoryPages(pages),
+            }
+            .into()),
+            _ => Ok(true),
+        }
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        desired: u32,
+        _maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        match self.limits.table_elements {
+            Some(elements) if desired > elements => Err(LimitExceeded {
+                cmd: self.cmd.clone(),
+                limit: Limit::TableElements(elements),
+            }
+            .into()),
+            _ => Ok(true),
+        }
+    }
+
+    fn instances(&self) -> usize {
+        self.limits
+            .instances
+            .unwrap_or(wasmtime::DEFAULT_INSTANCE_LIMIT)
+    }
+}
+
+#[derive(Debug)]
+pub enum Limit {
+    MemoryPages(u64),
+    TableElements(u32),
+}
+
+/// A command tried to grow a memory or table past its limit.
+#[derive(Debug)]
+pub struct LimitExceeded {
+    pub cmd: String,
+    pub limit: Limit,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            Limit::MemoryPages(pages) => {
+                write!(f, "{}: memory limit of {pages} pages exceeded", self.cmd)
+            }
+            Limit::TableElements(elements) => {
+                write!(
+                    f,
+                    "{}: table limit of {elements} elements exceeded",
+                    self.cmd
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
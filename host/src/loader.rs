@@ -0,0 +1,245 @@
+use std::fmt;
+
+use anyhow::Context;
+
+use crate::bindings::loader::LoaderWorld;
+use crate::cache::{self, DiskCache};
+use crate::config::{Limits, LoaderConfig, Preopen};
+use crate::limits;
+use crate::output::{self, Captured};
+
+struct LoaderHost {
+    ctx: wasmtime_wasi::WasiCtx,
+    table: wasmtime_wasi::ResourceTable,
+}
+
+impl wasmtime_wasi::WasiView for LoaderHost {
+    fn ctx(&mut self) -> &mut wasmtime_wasi::WasiCtx {
+        &mut self.ctx
+    }
+    fn table(&mut self) -> &mut wasmtime_wasi::ResourceTable {
+        &mut self.table
+    }
+}
+
+impl LoaderHost {
+    fn new(preopens: &[Preopen], captured: Option<&Captured>) -> anyhow::Result<Self> {
+        let mut builder = wasmtime_wasi::WasiCtxBuilder::new();
+        if let Some(captured) = captured {
+            builder
+                .stdout(captured.stdout.clone())
+                .stderr(captured.stderr.clone());
+        }
+        for preopen in preopens {
+            preopen.add_to(&mut builder, false)?;
+        }
+        Ok(Self {
+            ctx: builder.build(),
+            table: wasmtime_wasi::ResourceTable::new(),
+        })
+    }
+}
+
+/// A loader component together with the preopens it runs with. Like
+/// commands, every call gets a fresh store.
+pub struct Loader {
+    pub name: String,
+    engine: wasmtime::Engine,
+    linker: wasmtime::component::Linker<LoaderHost>,
+    component: wasmtime::component::Component,
+    preopens: Vec<Preopen>,
+    search_path: Vec<String>,
+    metered: bool,
+}
+
+impl Loader {
+    pub fn new(
+        engine: &wasmtime::Engine,
+        disk_cache: Option<&DiskCache>,
+        config: &LoaderConfig,
+        metered: bool,
+    ) -> anyhow::Result<Self> {
+        // Open the preopens once up front, to report missing directories
+        // at startup rather than on the first command.
+        LoaderHost::new(&config.preopens, None)?;
+        let mut linker = wasmtime::component::Linker::new(engine);
+        wasmtime_wasi::command::sync::add_to_linker(&mut linker)?;
+        let bytecode = std::fs::read(&config.path)
+            .with_context(|| format!("failed to read {}", config.path.display()))?;
+        let component = cache::compile(engine, disk_cache, &bytecode, &cache::digest(&bytecode))
+            .with_context(|| format!("failed to load {}", config.path.display()))?;
+        let loader = Self {
+            name: config.name.clone(),
+            engine: engine.clone(),
+            linker,
+            component,
+            preopens: config.preopens.clone(),
+            search_path: config.search_path.clone(),
+            metered,
+        };
+        // Likewise instantiate it once, to report a component that isn't
+        // a loader before anything is asked of it.
+        loader
+            .instantiate(&Captured::new())
+            .with_context(|| format!("{} is not a loader", config.path.display()))?;
+        Ok(loader)
+    }
+
+    /// Asks the loader for `cmd` in each search directory in turn, and
+    /// returns the first path that resolves with its bytecode, or why none
+    /// did. Names containing a `/` are passed to the loader as-is. A loader
+    /// that traps or is interrupted is an error rather than a miss, and
+    /// stops the search.
+    pub fn load(&self, cmd: &str) -> anyhow::Result<Result<(String, Vec<u8>), String>> {
+        let candidates = if cmd.contains('/') || self.search_path.is_empty() {
+            vec![cmd.to_string()]
+        } else {
+            self.search_path
+                .iter()
+                .map(|dir| format!("{}/{}", dir.trim_end_matches('/'), cmd))
+                .collect()
+        };
+        let mut errors = Vec::new();
+        for path in candidates {
+            match self
+                .call_load(&path)
+                .with_context(|| format!("{}: failed to load {path}", self.name))?
+            {
+                Ok(bytecode) => return Ok(Ok((path, bytecode))),
+                Err(msg) => errors.push(format!("{path}: {msg}")),
+            }
+        }
+        Ok(Err(errors.join("; ")))
+    }
+
+    /// A fresh instance of the loader, in a store of its own.
+    fn instantiate(
+        &self,
+        captured: &Captured,
+    ) -> anyhow::Result<(LoaderWorld, wasmtime::Store<LoaderHost>)> {
+        let mut store = self.store(captured)?;
+        let (loader, _) = LoaderWorld::instantiate(&mut store, &self.component, &self.linker)?;
+        Ok((loader, store))
+    }
+
+    fn store(&self, captured: &Captured) -> anyhow::Result<wasmtime::Store<LoaderHost>> {
+        let mut store = wasmtime::Store::new(
+            &self.engine,
+            LoaderHost::new(&self.preopens, Some(captured))?,
+        );
+        // Loaders have no limits, but can still be stopped with Ctrl-C.
+        limits::arm(&mut store, &Limits::default(), self.metered)?;
+        Ok(store)
+    }
+
+    /// Anything a loader prints to stdout while loading is dropped, and
+    /// anything it prints to stderr is shown labelled with its name.
+    fn call_load(&self, path: &str) -> anyhow::Result<Result<Vec<u8>, String>> {
+        let captured = Captured::new();
+        let (loader, mut store) = self.instantiate(&captured)?;
+        let result = loader.wasi_repl_host_loader().call_load(&mut store, path);
+        output::show_stderr(&self.name, &captured.stderr());
+        result
+    }
+
+    /// Calls the loader's `ls` export and returns what it printed.
+    pub fn ls(&self) -> anyhow::Result<String> {
+        let captured = Captured::new();
+        let (loader, mut store) = self.instantiate(&captured)?;
+        let result = loader.wasi_repl_host_loader().call_ls(&mut store);
+        output::show_stderr(&self.name, &captured.stderr());
+        if let Some(error) = result? {
+            anyhow::bail!(error);
+        }
+        Ok(captured.stdout())
+    }
+}
+
+/// Loaders tried in order until one of them resolves a command.
+pub struct LoaderChain {
+    loaders: Vec<Loader>,
+}
+
+/// Bytecode for a command, the loader that found it and the path it was
+/// found at.
+pub struct Loaded {
+    pub loader: String,
+    pub path: String,
+    pub bytecode: Vec<u8>,
+}
+
+impl Loaded {
+    /// Where the bytecode came from, for display.
+    pub fn source(&self) -> String {
+        format!("{} via {}", self.path, self.loader)
+    }
+}
+
+/// Every loader's reason for not resolving `cmd`.
+#[derive(Debug)]
+pub struct LoadError {
+    pub cmd: String,
+    pub attempts: Vec<(String, String)>,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: not found by any loader", self.cmd)?;
+        for (loader, msg) in &self.attempts {
+            write!(f, "\n  {loader}: {msg}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl LoaderChain {
+    pub fn new(
+        engine: &wasmtime::Engine,
+        disk_cache: Option<&DiskCache>,
+        configs: &[LoaderConfig],
+        metered: bool,
+    ) -> anyhow::Result<Self> {
+        let loaders = configs
+            .iter()
+            .map(|config| Loader::new(engine, disk_cache, config, metered))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { loaders })
+    }
+
+    pub fn len(&self) -> usize {
+        self.loaders.len()
+    }
+
+    /// The first loader's bytecode for `cmd`. A [`LoadError`] if none of
+    /// them has it, or whatever stopped a loader from answering.
+    pub fn load(&self, cmd: &str) -> anyhow::Result<Loaded> {
+        let mut attempts = Vec::new();
+        for loader in &self.loaders {
+            match loader.load(cmd)? {
+                Ok((path, bytecode)) => {
+                    return Ok(Loaded {
+                        loader: loader.name.clone(),
+                        path,
+                        bytecode,
+                    })
+                }
+                Err(msg) => attempts.push((loader.name.clone(), msg)),
+            }
+        }
+        Err(LoadError {
+            cmd: cmd.to_string(),
+            attempts,
+        }
+        .into())
+    }
+
+    /// Every loader's `ls` listing, in chain order.
+    pub fn ls(&self) -> Vec<(String, anyhow::Result<String>)> {
+        self.loaders
+            .iter()
+            .map(|loader| (loader.name.clone(), loader.ls()))
+            .collect()
+    }
+}
@@ -1,16 +1,51 @@
+mod bindings;
+mod builtins;
+mod cache;
+mod call;
+mod complete;
+mod config;
+mod grants;
+mod interrupt;
+mod limits;
+mod loader;
+mod output;
+mod parse;
+mod policy;
+mod value;
+mod wave;
+
 use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::rc::Rc;
 
 use anyhow::Context;
+use clap::Parser;
 use wasmtime::component;
 
-use std::path::Path;
+use bindings::command::CommandWorld;
+use bindings::structured::StructuredWorld;
+use cache::DiskCache;
+use config::{Config, LimitsConfig};
+use grants::Grants;
+use interrupt::Interrupted;
+use loader::LoaderChain;
+use output::Captured;
+use parse::{parse, Command, Connector, Env, Input, Pipeline, Redirect, Stdin};
+use policy::Policy;
+use value::Value;
 
-struct LoaderHost {
+struct CommandHost {
     ctx: wasmtime_wasi::WasiCtx,
     table: wasmtime_wasi::ResourceTable,
+    limiter: limits::Limiter,
+    /// Text `eval` returned for stderr, shown after anything the command
+    /// printed there.
+    returned_stderr: Option<String>,
 }
 
-impl wasmtime_wasi::WasiView for LoaderHost {
+impl wasmtime_wasi::WasiView for CommandHost {
     fn ctx(&mut self) -> &mut wasmtime_wasi::WasiCtx {
         &mut self.ctx
     }
@@ -19,181 +54,810 @@ impl wasmtime_wasi::WasiView for LoaderHost {
     }
 }
 
-impl LoaderHost {
-    fn new(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+impl CommandHost {
+    fn new(
+        argv: &[String],
+        grants: &Grants,
+        stdin: Option<Vec<u8>>,
+        captured: &Captured,
+        limiter: limits::Limiter,
+    ) -> anyhow::Result<Self> {
+        let mut builder = wasmtime_wasi::WasiCtxBuilder::new();
+        builder.args(argv);
+        grants.add_to(&mut builder)?;
+        // Without stdio, stdin stays closed and output goes nowhere.
+        if grants.stdio {
+            builder
+                .stdout(captured.stdout.clone())
+                .stderr(captured.stderr.clone());
+            if let Some(stdin) = stdin {
+                builder.stdin(wasmtime_wasi::pipe::MemoryInputPipe::new(stdin));
+            }
+        }
         Ok(Self {
-            ctx: wasmtime_wasi::WasiCtxBuilder::new()
-                .preopened_dir(
-                    wasi_common::sync::Dir::from_std_file(
-                        std::fs::File::open(dir.as_ref()).with_context(|| {
-                            format!("failed to open {}", dir.as_ref().to_str().unwrap())
-                        })?,
-                    ),
-                    wasmtime_wasi::DirPerms::READ,
-                    wasmtime_wasi::FilePerms::READ,
-                    "/",
-                )
-                .build(),
+            ctx: builder.build(),
             table: wasmtime_wasi::ResourceTable::new(),
+            limiter,
+            returned_stderr: None,
         })
     }
 }
 
-struct CommandHost {
-    ctx: wasmtime_wasi::WasiCtx,
-    table: wasmtime_wasi::ResourceTable,
+fn main() -> anyhow::Result<ExitCode> {
+    let mut args = config::Args::parse();
+    let script = match (args.script.take(), args.command.take()) {
+        (_, Some(command)) => Some(("-c".to_string(), command)),
+        (Some(path), None) if path.as_os_str() == "-" => {
+            let mut src = String::new();
+            std::io::stdin()
+                .read_to_string(&mut src)
+                .context("failed to read stdin")?;
+            Some(("-".to_string(), src))
+        }
+        (Some(path), None) => {
+            let src = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            Some((path.display().to_string(), src))
+        }
+        (None, None) => None,
+    };
+    let keep_going = args.keep_going;
+    let mut evaluator = Evaluator::new(&Config::load(args)?)?;
+    let status = match script {
+        Some((name, src)) => evaluator.script(&name, &src, keep_going),
+        None => evaluator.repl()?,
+    };
+    Ok(ExitCode::from(status))
 }
 
-impl wasmtime_wasi::WasiView for CommandHost {
-    fn ctx(&mut self) -> &mut wasmtime_wasi::WasiCtx {
-        &mut self.ctx
-    }
-    fn table(&mut self) -> &mut wasmtime_wasi::ResourceTable {
-        &mut self.table
-    }
+/// The interface commands export `eval` from.
+const COMMAND_INTERFACE: &str = "wasi-repl:host/command@0.2.0";
+
+/// The interface structured commands export `eval` from.
+const STRUCTURED_INTERFACE: &str = "wasi-repl:host/structured@0.2.0";
+
+/// A command that ran to completion but failed.
+#[derive(Debug)]
+struct Failed {
+    cmd: String,
+    /// Its exit status, never zero.
+    status: i32,
+    /// Why, if the command said.
+    message: Option<String>,
+    /// What it printed to stdout before failing, which goes wherever the
+    /// pipeline's output would have.
+    stdout: String,
 }
 
-impl CommandHost {
-    fn new() -> anyhow::Result<Self> {
-        Ok(Self {
-            ctx: wasmtime_wasi::WasiCtxBuilder::new().build(),
-            table: wasmtime_wasi::ResourceTable::new(),
-        })
+impl std::fmt::Display for Failed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {message}", self.cmd),
+            None => write!(f, "{}: exited with status {}", self.cmd, self.status),
+        }
     }
 }
 
-enum Input {
-    Command(String, Vec<String>),
-    Empty,
-    Exit,
+impl std::error::Error for Failed {}
+
+impl Failed {
+    /// How a command whose `eval` returned an error failed, after
+    /// printing `stdout`.
+    fn returned(cmd: &str, status: u8, message: String, stdout: String) -> Self {
+        Self {
+            cmd: cmd.to_string(),
+            status: i32::from(status.max(1)),
+            message: Some(message),
+            stdout,
+        }
+    }
 }
 
-fn parse(line: &str) -> Input {
-    let mut words = line.split(" ").filter(|a| *a != "");
-    match words.next() {
-        Some(cmd) => match cmd {
-            "exit" => Input::Exit,
-            cmd => Input::Command(cmd.to_string(), words.map(|a| a.to_string()).collect()),
-        },
-        None => Input::Empty,
+/// The exit status to keep in `$?` for an input that failed with `e`:
+/// the command's own if it gave one, 130 for Ctrl-C and 124 for a
+/// timeout, as shells have it, and 1 for anything else.
+fn exit_status(e: &anyhow::Error) -> i32 {
+    if let Some(failed) = e.downcast_ref::<Failed>() {
+        return failed.status;
+    }
+    match e.downcast_ref::<Interrupted>() {
+        Some(Interrupted::CtrlC) => 130,
+        Some(Interrupted::Timeout(_)) => 124,
+        None => 1,
     }
 }
 
-fn main() -> anyhow::Result<()> {
-    Evaluator::new()?.repl()
+/// How running one input went.
+#[derive(PartialEq)]
+enum Status {
+    Ok,
+    Failed,
+    /// Ctrl-C stopped it.
+    Interrupted,
+    /// `exit`, with the status it was given.
+    Exit(Option<u8>),
+}
+
+/// Where a pipeline stage's input comes from.
+enum StageInput {
+    None,
+    /// The previous stage's output. Structured commands get it as it is,
+    /// builtins and text `eval` commands get it rendered as their last
+    /// argument, and every command can read it rendered on stdin.
+    Piped(Value),
+    /// A `<` file or heredoc, read on stdin.
+    Stdin(Vec<u8>),
 }
 
 struct Evaluator {
     engine: wasmtime::Engine,
-    command_store: wasmtime::Store<CommandHost>,
     command_linker: wasmtime::component::Linker<CommandHost>,
-    loader_store: wasmtime::Store<LoaderHost>,
-    loader_linker: wasmtime::component::Linker<LoaderHost>,
-    loader_component: wasmtime::component::Component,
-    cache: std::collections::BTreeMap<String, wasmtime::component::Component>,
+    loaders: Rc<LoaderChain>,
+    /// Command names for completion at the prompt.
+    names: Rc<complete::Names>,
+    /// Where the prompt's history is kept, if anywhere.
+    history: Option<PathBuf>,
+    cache: std::collections::BTreeMap<String, Cached>,
+    disk_cache: Option<DiskCache>,
+    vars: BTreeMap<String, String>,
+    /// The exit status of the last input, as `$?`.
+    status: i32,
+    policy: Policy,
+    limits: LimitsConfig,
+    /// Whether the engine consumes fuel.
+    metered: bool,
+    /// The last command run and the fuel it used, when metered.
+    last_fuel: Option<(String, u64)>,
+    /// What commands wrote to stderr that hasn't been shown yet, with the
+    /// name of the command that wrote it.
+    stderr: Vec<(String, String)>,
+}
+
+/// A compiled command, where its bytecode came from, and the bytecode's
+/// SHA-256 digest.
+struct Cached {
+    component: wasmtime::component::Component,
+    source: String,
+    digest: [u8; 32],
+}
+
+impl Cached {
+    /// The first few bytes of the digest in hex, for display.
+    fn short_digest(&self) -> String {
+        cache::hex(&self.digest[..6])
+    }
 }
 
 impl Evaluator {
-    fn new() -> anyhow::Result<Self> {
-        let cache = BTreeMap::<String, wasmtime::component::Component>::new();
-        let engine = wasmtime::Engine::default();
-        let loader_store = wasmtime::Store::new(&engine, LoaderHost::new("../build")?);
-        let mut loader_linker = wasmtime::component::Linker::new(&engine);
-        wasmtime_wasi::command::sync::add_to_linker(&mut loader_linker)?;
-        let loader_component = wasmtime::component::Component::from_file(
-            &engine,
-            "../target/wasm32-wasi/debug/fs_loader.wasm",
-        )?;
-        let command_store = wasmtime::Store::new(&engine, CommandHost::new()?);
+    fn new(config: &Config) -> anyhow::Result<Self> {
+        let cache = BTreeMap::<String, Cached>::new();
+        let engine = config.engine.build()?;
+        interrupt::start(&engine)?;
+        let disk_cache = (config.engine.cache_dir.clone()).map(|dir| DiskCache::new(dir, &engine));
+        let policy = match &config.policy {
+            Some(path) => Policy::load(path)?,
+            None => Policy::default(),
+        };
+        let metered = config.engine.consume_fuel;
+        let loaders = LoaderChain::new(&engine, disk_cache.as_ref(), &config.loaders, metered)?;
+        let loaders = Rc::new(loaders);
+        let names = Rc::new(complete::Names::new(loaders.clone()));
         let mut command_linker = wasmtime::component::Linker::new(&engine);
         wasmtime_wasi::command::sync::add_to_linker(&mut command_linker)?;
         Ok(Self {
             engine,
             cache,
             command_linker,
-            command_store,
-            loader_linker,
-            loader_store,
-            loader_component,
+            loaders,
+            names,
+            history: config.history.clone(),
+            disk_cache,
+            vars: BTreeMap::new(),
+            status: 0,
+            policy,
+            limits: config.limits.clone(),
+            metered,
+            last_fuel: None,
+            stderr: Vec::new(),
         })
     }
 
-    fn repl(&mut self) -> anyhow::Result<()> {
-        let mut rl = rustyline::DefaultEditor::new()?;
+    /// Reads and runs inputs until `exit` or Ctrl-D, and returns the
+    /// status to exit with.
+    fn repl(&mut self) -> anyhow::Result<u8> {
+        let mut rl = rustyline::Editor::new()?;
+        rl.set_helper(Some(complete::Helper::new(self.names.clone())));
+        if let Some(path) = &self.history {
+            if path.exists() {
+                rl.load_history(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+            } else if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("failed to create {}", dir.display()))?;
+            }
+        }
+        let mut buffer = String::new();
         loop {
-            match rl.readline("> ") {
-                Err(rustyline::error::ReadlineError::Eof) => return Ok(()),
-                Err(rustyline::error::ReadlineError::Interrupted) => return Ok(()),
+            let prompt = if buffer.is_empty() { "> " } else { "... " };
+            match rl.readline(prompt) {
+                Err(rustyline::error::ReadlineError::Eof) => return Ok(0),
+                // Ctrl-C at the prompt abandons the current input; Ctrl-D
+                // is how to leave.
+                Err(rustyline::error::ReadlineError::Interrupted) => buffer.clear(),
                 Err(e) => anyhow::bail!(e),
-                Ok(line) => match parse(&line) {
-                    Input::Exit => return Ok(()),
-                    Input::Empty => {}
-                    Input::Command(cmd, args) => {
-                        println!("{}", self.eval(cmd, args)?)
+                Ok(line) => {
+                    buffer.push_str(&line);
+                    let parsed = parse(&buffer, self);
+                    if matches!(&parsed, Err(e) if e.is_incomplete()) {
+                        buffer.push('\n');
+                        continue;
+                    }
+                    self.add_history(&mut rl, &buffer);
+                    buffer.clear();
+                    let status = self.execute(parsed, "");
+                    self.names.set_cached(self.cache.keys().cloned());
+                    if let Status::Exit(status) = status {
+                        return Ok(status.unwrap_or(0));
                     }
-                },
+                }
             }
         }
     }
 
-    fn eval(&mut self, cmd: String, args: Vec<String>) -> anyhow::Result<String> {
-        let result = match self.cache.get(&cmd) {
-            Some(component) => match self
-                .command_linker
-                .instantiate(&mut self.command_store, &component)?
-                .get_typed_func::<(Vec<String>,), (String,)>(&mut self.command_store, "eval")
-                .expect("no export func `eval`")
-                .call(&mut self.command_store, (args,))
-            {
-                Err(e) => e.to_string(),
-                Ok((msg,)) => msg,
-            },
-            None => match self
-                .loader_linker
-                .instantiate(&mut self.loader_store, &self.loader_component)?
-                .get_typed_func::<(String,), (Result<Vec<u8>, String>,)>(
-                    &mut self.loader_store,
-                    "load",
-                )
-                .expect("no export `load`")
-                .call(&mut self.loader_store, (cmd.clone(),))
-            {
-                Err(e) => e.to_string(),
-                Ok((Err(msg),)) => msg,
-                Ok((Ok(bytecode),)) => {
-                    match wasmtime::component::Component::from_binary(&self.engine, &bytecode) {
-                        Err(e) => e.to_string(),
-                        Ok(component) => {
-                            self.cache.insert(cmd, component.clone());
-                            self.exec_component(args, component)?
-                        }
+    /// Records `input` in the history and saves it right away, so history
+    /// survives the REPL being killed. Failing to save isn't fatal.
+    fn add_history(
+        &self,
+        rl: &mut rustyline::Editor<complete::Helper, rustyline::history::DefaultHistory>,
+        input: &str,
+    ) {
+        if input.trim().is_empty() || !rl.add_history_entry(input).unwrap_or(false) {
+            return;
+        }
+        if let Some(path) = &self.history {
+            if let Err(e) = rl.append_history(path) {
+                eprintln!("failed to save history to {}: {e}", path.display());
+            }
+        }
+    }
+
+    /// Runs `src` without prompting, as if each line had been typed at the
+    /// REPL. Stops at the first input that fails unless `keep_going` is
+    /// set. Returns the status `exit` gave, or else 0 if every input
+    /// succeeded and 1 if not. Errors are reported with `name` and the
+    /// line the input starts on.
+    fn script(&mut self, name: &str, src: &str, keep_going: bool) -> u8 {
+        let mut buffer = String::new();
+        let mut start = 1;
+        let mut ok = true;
+        let mut lines = src.lines().enumerate().peekable();
+        while let Some((i, line)) = lines.next() {
+            if buffer.is_empty() {
+                start = i + 1;
+            }
+            buffer.push_str(line);
+            let parsed = parse(&buffer, self);
+            // At the end of the script an incomplete input is an error.
+            if matches!(&parsed, Err(e) if e.is_incomplete()) && lines.peek().is_some() {
+                buffer.push('\n');
+                continue;
+            }
+            buffer.clear();
+            match self.execute(parsed, &format!("{name}:{start}: ")) {
+                Status::Ok => {}
+                Status::Failed => ok = false,
+                // Ctrl-C stops the script, even with `keep_going`.
+                Status::Interrupted => return 1,
+                Status::Exit(Some(status)) => return status,
+                // A bare `exit` fails the script if the pipeline before it
+                // did, as after `||`.
+                Status::Exit(None) => return u8::from(!ok || self.status != 0),
+            }
+            // Ctrl-C between commands stops the script too.
+            if interrupt::take() {
+                eprintln!("{name}:{start}: interrupted");
+                return 1;
+            }
+            if !ok && !keep_going {
+                return 1;
+            }
+        }
+        u8::from(!ok)
+    }
+
+    /// Runs one complete input, reporting any error prefixed with `at`.
+    /// In a chain, each pipeline after `&&` runs only if the last one run
+    /// succeeded, and each after `||` only if it failed.
+    fn execute(&mut self, parsed: Result<Input, parse::ParseError>, at: &str) -> Status {
+        interrupt::clear();
+        let input = match parsed {
+            Ok(input) => input,
+            Err(e) => return self.parse_failed(e, at),
+        };
+        match input {
+            Input::Exit(args) => return self.exit(&args, at),
+            Input::Empty => return Status::Ok,
+            Input::Assign(name, value) => {
+                self.vars.insert(name, value);
+                self.status = 0;
+            }
+            Input::Pipeline(pipeline) => {
+                if self.run_reported(pipeline, at) == Status::Interrupted {
+                    return Status::Interrupted;
+                }
+            }
+            Input::Chain(links) => {
+                for link in links {
+                    let wanted = match link.after {
+                        None => true,
+                        Some(Connector::And) => self.status == 0,
+                        Some(Connector::Or) => self.status != 0,
+                    };
+                    if !wanted {
+                        continue;
+                    }
+                    let is_exit = link.is_exit();
+                    let mut pipeline = match link.expand(self) {
+                        Ok(pipeline) => pipeline,
+                        Err(e) => return self.parse_failed(e, at),
+                    };
+                    if is_exit {
+                        return self.exit(&pipeline.commands.remove(0).args, at);
+                    }
+                    if self.run_reported(pipeline, at) == Status::Interrupted {
+                        return Status::Interrupted;
                     }
                 }
+            }
+        }
+        match self.status {
+            0 => Status::Ok,
+            _ => Status::Failed,
+        }
+    }
+
+    /// The status for `exit` with `args`, or a failure if they aren't a
+    /// status.
+    fn exit(&mut self, args: &[String], at: &str) -> Status {
+        match builtins::exit_status(args) {
+            Ok(status) => Status::Exit(status),
+            Err(e) => {
+                eprintln!("{at}{}: {e:#}", output::error_label("error"));
+                self.status = 1;
+                Status::Failed
+            }
+        }
+    }
+
+    /// Reports `e` prefixed with `at` and keeps its exit status for `$?`.
+    /// Ctrl-C during a substitution counts as interrupting the input.
+    fn parse_failed(&mut self, e: parse::ParseError, at: &str) -> Status {
+        if matches!(e.kind, parse::ErrorKind::Interrupted) {
+            let e = anyhow::Error::from(Interrupted::CtrlC);
+            eprintln!("{at}{e}");
+            self.status = exit_status(&e);
+            return Status::Interrupted;
+        }
+        eprintln!("{at}{}: {e}", output::error_label("parse error"));
+        self.status = 1;
+        Status::Failed
+    }
+
+    /// Runs `pipeline`, keeps its exit status for `$?` and reports any
+    /// error prefixed with `at`. Ctrl-C stopping it stops the rest of a
+    /// chain too.
+    fn run_reported(&mut self, pipeline: Pipeline, at: &str) -> Status {
+        let Err(e) = self.run(pipeline) else {
+            self.status = 0;
+            return Status::Ok;
+        };
+        self.status = exit_status(&e);
+        match e.downcast_ref::<Interrupted>() {
+            Some(interrupted) => {
+                eprintln!("{at}{interrupted}");
+                match interrupted {
+                    Interrupted::CtrlC => Status::Interrupted,
+                    Interrupted::Timeout(_) => Status::Failed,
+                }
+            }
+            None => {
+                eprintln!("{at}{}: {e:#}", output::error_label("error"));
+                Status::Failed
+            }
+        }
+    }
+
+    /// Evaluates `pipeline` and prints its output, or writes it to the
+    /// redirection target. Stderr is shown first, even if the pipeline
+    /// failed, and so is whatever a failed command printed to stdout. The
+    /// host opens the files, so commands never need write access of their
+    /// own.
+    fn run(&mut self, pipeline: Pipeline) -> anyhow::Result<()> {
+        let output = self.eval_pipeline(pipeline.commands, pipeline.stdin);
+        match pipeline.errors {
+            None => self.show_stderr(),
+            Some(redirect) => {
+                let errors: String = self.stderr.drain(..).map(|(_, text)| text).collect();
+                write_redirect(&redirect, &errors)?;
+            }
+        }
+        let (output, result) = match output {
+            Ok(output) => {
+                let mut output = output.to_string();
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+                (output, Ok(()))
+            }
+            Err(e) => {
+                let stdout = e
+                    .downcast_ref::<Failed>()
+                    .map(|failed| failed.stdout.clone());
+                (stdout.unwrap_or_default(), Err(e))
+            }
+        };
+        // Like a `2>` file, a `>` file is written even when there is
+        // nothing to put in it.
+        match pipeline.output {
+            None => print!("{output}"),
+            Some(redirect) => write_redirect(&redirect, &output)?,
+        }
+        result
+    }
+
+    /// Prints and forgets what commands have written to stderr.
+    fn show_stderr(&mut self) {
+        for (cmd, text) in self.stderr.drain(..) {
+            output::show_stderr(&cmd, &text);
+        }
+    }
+
+    /// Runs each stage in turn, feeding it the previous stage's output.
+    /// The first stage reads `stdin`, which the host opens for it. Stops
+    /// at the first stage that fails.
+    fn eval_pipeline(
+        &mut self,
+        pipeline: Vec<Command>,
+        stdin: Option<Stdin>,
+    ) -> anyhow::Result<Value> {
+        let mut input = match stdin {
+            None => StageInput::None,
+            Some(Stdin::File(path)) => StageInput::Stdin(
+                std::fs::read(&path).with_context(|| format!("failed to read {path}"))?,
+            ),
+            Some(Stdin::Text(text)) => StageInput::Stdin(text.into_bytes()),
+        };
+        for Command { name, args } in pipeline {
+            input = StageInput::Piped(self.eval(name, args, input)?);
+        }
+        match input {
+            StageInput::Piped(output) => Ok(output),
+            _ => Ok(Value::String(String::new())),
+        }
+    }
+
+    fn eval(&mut self, cmd: String, args: Vec<String>, input: StageInput) -> anyhow::Result<Value> {
+        let (flags, cmd, mut args) = Grants::split(cmd, args)?;
+        if let Some(builtin) = builtins::find(&cmd) {
+            if !flags.is_empty() {
+                anyhow::bail!("{cmd}: builtins can't be granted anything");
+            }
+            match input {
+                StageInput::None => {}
+                StageInput::Piped(output) => args.push(output.to_string()),
+                StageInput::Stdin(_) => anyhow::bail!("{cmd}: builtins don't read stdin"),
+            }
+            return builtin.run(self, args).map(Value::String);
+        }
+        let (component, _, grants) = self.prepare(&cmd, flags)?;
+        let (piped, stdin) = match input {
+            StageInput::None => (None, None),
+            StageInput::Piped(output) => {
+                // Put back the newline the output was trimmed of.
+                let mut stdin = output.to_string().into_bytes();
+                if !stdin.is_empty() {
+                    stdin.push(b'\n');
+                }
+                (Some(output), Some(stdin))
+            }
+            StageInput::Stdin(stdin) => (None, Some(stdin)),
+        };
+        self.exec_component(
+            &cmd,
+            &args.clone(),
+            &grants,
+            stdin,
+            |evaluator, store, captured| {
+                evaluator.invoke(&cmd, store, &component, args, piped, captured)
             },
+        )
+    }
+
+    /// Calls the function `export` names in the component `cmd`, with
+    /// `args` in WAVE syntax. The output is whatever it printed to stdout,
+    /// followed by its results in WAVE syntax, one per line. Grant flags
+    /// may come before `cmd`, as when running it.
+    fn call(&mut self, cmd: String, args: Vec<String>) -> anyhow::Result<String> {
+        let (flags, cmd, args) = Grants::split(cmd, args)?;
+        let Some((export, args)) = args.split_first() else {
+            anyhow::bail!("usage: {}", builtins::usage("call"));
         };
-        Ok(result)
+        if builtins::find(&cmd).is_some() {
+            anyhow::bail!("{cmd}: builtins export no functions");
+        }
+        let (component, bytecode, grants) = self.prepare(&cmd, flags)?;
+        let exports = call::exports(&bytecode).with_context(|| format!("failed to read {cmd}"))?;
+        let output =
+            self.exec_component(&cmd, args, &grants, None, |evaluator, store, captured| {
+                let instance = evaluator
+                    .command_linker
+                    .instantiate(&mut *store, &component)?;
+                let func =
+                    call::find(store, &instance, &exports, export).with_context(|| cmd.clone())?;
+                let results =
+                    call::call(store, &func, args).with_context(|| format!("{cmd}: {export}"))?;
+                let mut output = captured.stdout();
+                if !output.is_empty() && !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                for result in &results {
+                    output.push_str(&wave::print(result));
+                    output.push('\n');
+                }
+                Ok(Value::String(trim_newline(output)))
+            })?;
+        Ok(output.to_string())
     }
 
+    /// Loads `cmd` and compiles it, unless the cache has it compiled from
+    /// the same bytecode. Returns the component, its bytecode and the
+    /// grants the policy gives it, with `flags` added.
+    fn prepare(
+        &mut self,
+        cmd: &str,
+        flags: Grants,
+    ) -> anyhow::Result<(component::Component, Vec<u8>, Grants)> {
+        // Ask the loader every time, so that a command rebuilt since it
+        // was cached is picked up. Compiling is what the caches save.
+        let loaded = self.loaders.load(cmd)?;
+        let digest = cache::digest(&loaded.bytecode);
+        let component = match self.cache.get(cmd) {
+            Some(cached) if cached.digest == digest => cached.component.clone(),
+            stale => {
+                let component = cache::compile(
+                    &self.engine,
+                    self.disk_cache.as_ref(),
+                    &loaded.bytecode,
+                    &digest,
+                )
+                .with_context(|| format!("failed to compile {cmd}"))?;
+                if stale.is_some() {
+                    eprintln!("{cmd}: bytecode changed, reloaded");
+                } else if self.loaders.len() > 1 {
+                    eprintln!("{cmd}: {}", loaded.source());
+                }
+                self.cache.insert(
+                    cmd.to_string(),
+                    Cached {
+                        component: component.clone(),
+                        source: loaded.source(),
+                        digest,
+                    },
+                );
+                component
+            }
+        };
+        // Flags add to the policy for this invocation only.
+        let mut grants = self.policy.for_command(cmd, &digest).0.clone();
+        grants.extend(flags);
+        Ok((component, loaded.bytecode, grants))
+    }
+
+    /// Runs `run` with a store for the command `cmd`, set up with `args`
+    /// after its name as its argv, `grants`, `stdin` and its limits, and
+    /// keeps what it wrote to stderr and the fuel it used.
     fn exec_component(
         &mut self,
-        args: Vec<String>,
-        component: component::Component,
-    ) -> anyhow::Result<String> {
-        let result = match self
-            .command_linker
-            .instantiate(&mut self.command_store, &component)
-        {
-            Err(e) => e.to_string(),
-            Ok(inst) => match inst
-                .get_typed_func::<(Vec<String>,), (String,)>(&mut self.command_store, "eval")
-            {
-                Err(e) => e.to_string(),
-                Ok(func) => match func.call(&mut self.command_store, (args,)) {
-                    Err(e) => e.to_string(),
-                    Ok((msg,)) => msg,
-                },
+        cmd: &str,
+        args: &[String],
+        grants: &Grants,
+        stdin: Option<Vec<u8>>,
+        run: impl FnOnce(&Self, &mut wasmtime::Store<CommandHost>, &Captured) -> anyhow::Result<Value>,
+    ) -> anyhow::Result<Value> {
+        // Every invocation gets a store of its own, so commands can't see
+        // each other's state and everything they allocated is freed when
+        // the store is dropped at the end of the call.
+        let limits = self.limits.for_command(cmd);
+        let captured = Captured::new();
+        let argv: Vec<String> = std::iter::once(cmd.to_string())
+            .chain(args.iter().cloned())
+            .collect();
+        let host = CommandHost::new(
+            &argv,
+            grants,
+            stdin,
+            &captured,
+            limits::Limiter::new(cmd, &limits),
+        )?;
+        let mut store = wasmtime::Store::new(&self.engine, host);
+        store.limiter(|host| &mut host.limiter);
+        limits::arm(&mut store, &limits, self.metered)?;
+        let result = run(self, &mut store, &captured);
+        let mut stderr = captured.stderr();
+        if let Some(text) = store.data_mut().returned_stderr.take() {
+            push_lines(&mut stderr, &text);
+        }
+        if !stderr.is_empty() {
+            self.stderr.push((cmd.to_string(), stderr));
+        }
+        if self.metered {
+            let budget = limits.fuel.unwrap_or(u64::MAX);
+            self.last_fuel = Some((cmd.to_string(), budget - store.get_fuel()?));
+        }
+        match result {
+            Ok(value) => Ok(value),
+            Err(e) if e.downcast_ref() == Some(&wasmtime::Trap::OutOfFuel) => {
+                Err(limits::OutOfFuel {
+                    cmd: cmd.to_string(),
+                    budget: limits.fuel.unwrap_or(u64::MAX),
+                }
+                .into())
+            }
+            // Drop the wasm backtrace; the limit is all there is to say.
+            Err(e) => match e.downcast::<limits::LimitExceeded>() {
+                Ok(exceeded) => Err(exceeded.into()),
+                Err(e) => Err(e),
             },
+        }
+    }
+
+    /// Calls the component's structured `eval` with `args` and `piped` as
+    /// its input, or its text `eval` with `piped` rendered and appended to
+    /// `args`, whichever it exports. Otherwise runs it as a `wasi:cli/run`
+    /// program with the argv in `store`. The output of a text command is
+    /// whatever it printed to stdout, followed by the output `eval`
+    /// returned; what a structured command prints to stdout is shown
+    /// labelled, like its stderr.
+    /// A command that fails is reported as [`Failed`], carrying its
+    /// stdout.
+    fn invoke(
+        &self,
+        cmd: &str,
+        store: &mut wasmtime::Store<CommandHost>,
+        component: &component::Component,
+        mut args: Vec<String>,
+        piped: Option<Value>,
+        captured: &Captured,
+    ) -> anyhow::Result<Value> {
+        let instance = self.command_linker.instantiate(&mut *store, component)?;
+        let mut exports = |name| instance.exports(&mut *store).instance(name).is_some();
+        if exports(STRUCTURED_INTERFACE) {
+            let command = StructuredWorld::new(&mut *store, &instance)
+                .with_context(|| format!("{cmd}: doesn't match `{STRUCTURED_INTERFACE}`"))?;
+            let input = piped.map(|value| value.to_nodes());
+            let outcome = command.wasi_repl_host_structured().call_eval(
+                &mut *store,
+                &args,
+                input.as_ref(),
+            )?;
+            // Its output is the value alone, so show what it printed to
+            // stdout with its stderr rather than lose it.
+            let mut stderr = captured.stdout();
+            if let Some(text) = outcome.stderr {
+                push_lines(&mut stderr, &text);
+            }
+            store.data_mut().returned_stderr = Some(stderr).filter(|text| !text.is_empty());
+            let nodes = outcome
+                .output
+                .map_err(|message| Failed::returned(cmd, outcome.status, message, String::new()))?;
+            let value = Value::from_nodes(nodes)
+                .map_err(|e| anyhow::anyhow!("{cmd}: returned a malformed value: {e}"))?;
+            return Ok(value);
+        }
+        if exports(COMMAND_INTERFACE) {
+            let command = CommandWorld::new(&mut *store, &instance)
+                .with_context(|| format!("{cmd}: doesn't match `{COMMAND_INTERFACE}`"))?;
+            args.extend(piped.map(|value| value.to_string()));
+            let outcome = command
+                .wasi_repl_host_command()
+                .call_eval(&mut *store, &args)?;
+            store.data_mut().returned_stderr = outcome.stderr;
+            let mut output = captured.stdout();
+            match outcome.output {
+                Ok(msg) => output.push_str(&msg),
+                Err(message) => {
+                    return Err(Failed::returned(cmd, outcome.status, message, output).into());
+                }
+            }
+            return Ok(Value::String(trim_newline(output)));
+        }
+        let Ok(command) = wasmtime_wasi::command::sync::Command::new(&mut *store, &instance) else {
+            anyhow::bail!(
+                "{cmd}: exports none of `{STRUCTURED_INTERFACE}`, `{COMMAND_INTERFACE}` \
+                 and `wasi:cli/run`"
+            );
+        };
+        let code = match command.wasi_cli_run().call_run(&mut *store) {
+            Ok(Ok(())) => 0,
+            Ok(Err(())) => 1,
+            Err(e) => match e.downcast_ref::<wasmtime_wasi::I32Exit>() {
+                Some(exit) => exit.0,
+                None => return Err(e),
+            },
+        };
+        let stdout = captured.stdout();
+        if code != 0 {
+            return Err(Failed {
+                cmd: cmd.to_string(),
+                status: code,
+                message: None,
+                stdout,
+            }
+            .into());
+        }
+        Ok(Value::String(trim_newline(stdout)))
+    }
+}
+
+/// Appends `more` to `text`, starting it on a new line.
+fn push_lines(text: &mut String, more: &str) {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(more);
+}
+
+/// Drops one trailing newline, which the REPL adds back when printing.
+fn trim_newline(mut output: String) -> String {
+    if output.ends_with('\n') {
+        output.pop();
+    }
+    output
+}
+
+/// Writes `text` to the file `redirect` names, creating it if needed.
+fn write_redirect(redirect: &Redirect, text: &str) -> anyhow::Result<()> {
+    let Redirect { path, append } = redirect;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(*append)
+        .truncate(!append)
+        .open(path)
+        .with_context(|| format!("failed to open {path}"))?;
+    file.write_all(text.as_bytes())
+        .with_context(|| format!("failed to write {path}"))
+}
+
+impl Env for Evaluator {
+    fn var(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(self.status.to_string()),
+            _ => self.vars.get(name).cloned(),
+        }
+    }
+
+    fn capture(&mut self, src: &str) -> anyhow::Result<String> {
+        let input = match parse(src, self) {
+            // Pass Ctrl-C in a nested substitution on as it is.
+            Err(e) if matches!(e.kind, parse::ErrorKind::Interrupted) => {
+                return Err(Interrupted::CtrlC.into())
+            }
+            parsed => parsed?,
         };
-        Ok(result)
+        match input {
+            Input::Pipeline(Pipeline {
+                commands,
+                stdin,
+                output: None,
+                errors: None,
+            }) => {
+                let output = self.eval_pipeline(commands, stdin);
+                self.show_stderr();
+                output.map(|value| value.to_string())
+            }
+            Input::Empty => Ok(String::new()),
+            _ => anyhow::bail!("`$(...)` must contain only commands"),
+        }
     }
 }
@@ -0,0 +1,48 @@
+use std::io::{IsTerminal, Write};
+
+use wasmtime_wasi::pipe::MemoryOutputPipe;
+
+/// Most output the host keeps from each of a guest's stdout and stderr.
+const LIMIT: usize = 16 << 20;
+
+/// A guest's stdout and stderr, kept in memory instead of going to the
+/// terminal.
+pub struct Captured {
+    pub stdout: MemoryOutputPipe,
+    pub stderr: MemoryOutputPipe,
+}
+
+impl Captured {
+    pub fn new() -> Self {
+        Self {
+            stdout: MemoryOutputPipe::new(LIMIT),
+            stderr: MemoryOutputPipe::new(LIMIT),
+        }
+    }
+
+    pub fn stdout(&self) -> String {
+        String::from_utf8_lossy(&self.stdout.contents()).into_owned()
+    }
+
+    pub fn stderr(&self) -> String {
+        String::from_utf8_lossy(&self.stderr.contents()).into_owned()
+    }
+}
+
+/// Prints what `label` wrote to stderr, each line prefixed with the label
+/// so that it can't be mistaken for output.
+pub fn show_stderr(label: &str, text: &str) {
+    let mut stderr = std::io::stderr().lock();
+    for line in text.lines() {
+        let _ = writeln!(stderr, "[{label}] {line}");
+    }
+}
+
+/// `label` in bold red if stderr is a terminal, to start an error message
+/// with.
+pub fn error_label(label: &str) -> String {
+    match std::io::stderr().is_terminal() {
+        true => format!("\x1b[1;31m{label}\x1b[0m"),
+        false => label.to_string(),
+    }
+}
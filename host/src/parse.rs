@@ -0,0 +1,1151 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::CharIndices;
+
+use crate::interrupt::Interrupted;
+
+pub enum Input {
+    Pipeline(Pipeline),
+    /// Pipelines joined by `&&` and `||`, in order.
+    Chain(Vec<Link>),
+    /// `NAME = VALUE`
+    Assign(String, String),
+    Empty,
+    /// `exit` on its own, with its arguments.
+    Exit(Vec<String>),
+}
+
+/// Commands joined by `|`, with optional `>`/`>>` and `2>`/`2>>`
+/// redirections at the end, and an optional `<` or heredoc for the
+/// first command.
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+    /// What the first command reads on stdin.
+    pub stdin: Option<Stdin>,
+    pub output: Option<Redirect>,
+    /// Where everything the commands write to stderr goes.
+    pub errors: Option<Redirect>,
+}
+
+/// One pipeline of a chain. Unlike a lone pipeline it is expanded just
+/// before it runs, so that `$?` and `$(...)` see what earlier pipelines
+/// did.
+pub struct Link {
+    /// The operator before the pipeline, `None` for the first one.
+    pub after: Option<Connector>,
+    src: Rc<str>,
+    spec: PipelineSpec,
+}
+
+impl Link {
+    /// Whether the pipeline is `exit` on its own, with or without
+    /// arguments.
+    pub fn is_exit(&self) -> bool {
+        self.spec.is_exit()
+    }
+
+    pub fn expand(self, env: &mut dyn Env) -> Result<Pipeline, ParseError> {
+        self.spec.expand(&self.src, env)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Connector {
+    /// `&&`: run if the previous pipeline succeeded.
+    And,
+    /// `||`: run if it failed.
+    Or,
+}
+
+impl Connector {
+    fn as_str(self) -> &'static str {
+        match self {
+            Connector::And => "&&",
+            Connector::Or => "||",
+        }
+    }
+}
+
+/// A file the host writes a pipeline's output to.
+pub struct Redirect {
+    pub path: String,
+    pub append: bool,
+}
+
+/// Input for the first command of a pipeline.
+pub enum Stdin {
+    /// `< FILE`
+    File(String),
+    /// The body of a `<<DELIMITER` heredoc, taken literally.
+    Text(String),
+}
+
+/// One stage of a pipeline.
+pub struct Command {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// What `$` expansion needs from the evaluator.
+pub trait Env {
+    fn var(&self, name: &str) -> Option<String>;
+    /// Runs the source of a `$(...)` substitution and returns its output.
+    fn capture(&mut self, src: &str) -> anyhow::Result<String>;
+}
+
+pub enum Token {
+    Word(Word),
+    Pipe,
+    /// `&&` or `||`
+    Connector(Connector),
+    /// `>`, or `>>` when `append` is set. With `stderr` set, `2>` or
+    /// `2>>`.
+    Output {
+        append: bool,
+        stderr: bool,
+    },
+    /// `<`
+    Input,
+    /// A `<<DELIMITER` heredoc, with its body.
+    Heredoc(String),
+}
+
+/// A word as written, before `$` expansion.
+#[derive(Default)]
+pub struct Word {
+    parts: Vec<Part>,
+    /// Whether any of the word was quoted or escaped.
+    quoted: bool,
+}
+
+enum Part {
+    Text(String),
+    Var { name: String, pos: usize },
+    Subst { src: String, pos: usize },
+}
+
+impl Word {
+    fn push(&mut self, c: char) {
+        match self.parts.last_mut() {
+            Some(Part::Text(text)) => text.push(c),
+            _ => self.parts.push(Part::Text(c.to_string())),
+        }
+    }
+
+    /// The word's text, if it was written without quotes or expansions.
+    fn bare(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [Part::Text(text)] if !self.quoted => Some(text),
+            _ => None,
+        }
+    }
+
+    fn expand(&self, src: &str, env: &mut dyn Env) -> Result<String, ParseError> {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Var { name, pos } => match env.var(name) {
+                    Some(value) => out.push_str(&value),
+                    None => {
+                        let kind = ErrorKind::UndefinedVariable(name.clone());
+                        return Err(ParseError::new(kind, src, *pos));
+                    }
+                },
+                Part::Subst { src: inner, pos } => match env.capture(inner) {
+                    Ok(value) => out.push_str(value.trim_end_matches('\n')),
+                    Err(e) => {
+                        // Ctrl-C has to stay recognisable, so that it can
+                        // stop a script.
+                        let kind = match e.downcast_ref::<Interrupted>() {
+                            Some(Interrupted::CtrlC) => ErrorKind::Interrupted,
+                            _ => ErrorKind::Substitution(format!("{e:#}")),
+                        };
+                        return Err(ParseError::new(kind, src, *pos));
+                    }
+                },
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Whether `name` can be used as a variable name.
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A parse failure at a 1-based line and column of the input.
+#[derive(Debug)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    UnterminatedQuote(char),
+    UnterminatedSubstitution,
+    UnterminatedBrace,
+    UnterminatedHeredoc(String),
+    TrailingBackslash,
+    TrailingOperator(&'static str),
+    EmptyCommand(&'static str),
+    MissingRedirectTarget(&'static str),
+    DuplicateRedirect(&'static str),
+    PipeAfterRedirect,
+    InputAfterPipe,
+    BadAssignment,
+    BadVariableName(String),
+    UndefinedVariable(String),
+    Substitution(String),
+    /// Ctrl-C stopped a substitution.
+    Interrupted,
+}
+
+impl ParseError {
+    fn new(kind: ErrorKind, src: &str, pos: usize) -> Self {
+        let before = &src[..pos];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        Self { kind, line, column }
+    }
+
+    /// Whether more input could complete the line, so the REPL should
+    /// prompt for a continuation instead of reporting the error.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            self.kind,
+            ErrorKind::UnterminatedQuote(_)
+                | ErrorKind::UnterminatedSubstitution
+                | ErrorKind::UnterminatedHeredoc(_)
+                | ErrorKind::TrailingBackslash
+                | ErrorKind::TrailingOperator(_)
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::UnterminatedQuote('\'') => write!(f, "unterminated single quote")?,
+            ErrorKind::UnterminatedQuote(_) => write!(f, "unterminated double quote")?,
+            ErrorKind::UnterminatedSubstitution => write!(f, "unterminated `$(`")?,
+            ErrorKind::UnterminatedBrace => write!(f, "unterminated `${{`")?,
+            ErrorKind::UnterminatedHeredoc(delimiter) => {
+                write!(f, "heredoc is missing its `{delimiter}` line")?
+            }
+            ErrorKind::TrailingBackslash => write!(f, "backslash at end of input")?,
+            ErrorKind::TrailingOperator(op) => write!(f, "`{op}` at end of input")?,
+            ErrorKind::EmptyCommand(op) => write!(f, "missing command before `{op}`")?,
+            ErrorKind::MissingRedirectTarget("<<") => write!(f, "missing delimiter after `<<`")?,
+            ErrorKind::MissingRedirectTarget(op) => write!(f, "missing file name after `{op}`")?,
+            ErrorKind::DuplicateRedirect(stream) => write!(f, "{stream} is already redirected")?,
+            ErrorKind::PipeAfterRedirect => write!(f, "`|` after an output redirection")?,
+            ErrorKind::InputAfterPipe => write!(f, "only the first command can read input")?,
+            ErrorKind::BadAssignment => write!(f, "expected `NAME = VALUE`")?,
+            ErrorKind::BadVariableName(name) => write!(f, "bad variable name `{name}`")?,
+            ErrorKind::UndefinedVariable(name) => write!(f, "undefined variable `{name}`")?,
+            ErrorKind::Substitution(msg) => write!(f, "substitution failed: {msg}")?,
+            ErrorKind::Interrupted => write!(f, "substitution interrupted")?,
+        }
+        if self.line > 1 {
+            write!(f, " at line {}, column {}", self.line, self.column)
+        } else {
+            write!(f, " at column {}", self.column)
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Splits `src` into words and operators, the way a POSIX shell would:
+/// blanks separate words, single quotes are literal, double quotes allow
+/// `\"`, `\\` and `\$` escapes, and a backslash-newline joins two lines.
+/// `$NAME`, `${NAME}`, `$?` and `$(...)` are recorded in the word for
+/// expansion later. A lone `&` is an ordinary character. A `#` that
+/// starts a word begins a comment running to the end of the line. A
+/// heredoc's body is the lines after the one its `<<` is on, up to a line
+/// holding just the delimiter. Each token is paired with the byte offset
+/// it starts at.
+pub fn lex(src: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut word: Option<(usize, Word)> = None;
+    // Heredocs on the current line: the index of each one's token, its
+    // delimiter and where its `<<` is.
+    let mut heredocs: Vec<(usize, String, usize)> = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if let Some((start, word)) = word.take() {
+                    tokens.push((start, Token::Word(word)));
+                }
+                if c == '\n' {
+                    for (index, delimiter, pos) in heredocs.drain(..) {
+                        let body = lex_heredoc(&mut chars, &delimiter).ok_or_else(|| {
+                            ParseError::new(ErrorKind::UnterminatedHeredoc(delimiter), src, pos)
+                        })?;
+                        tokens[index].1 = Token::Heredoc(body);
+                    }
+                }
+            }
+            '<' => {
+                if let Some((start, word)) = word.take() {
+                    tokens.push((start, Token::Word(word)));
+                }
+                if chars.next_if(|&(_, c)| c == '<').is_none() {
+                    tokens.push((pos, Token::Input));
+                    continue;
+                }
+                while chars.next_if(|&(_, c)| c == ' ' || c == '\t').is_some() {}
+                let mut delimiter = String::new();
+                while let Some((_, c)) = chars.next_if(|&(_, c)| !" \t\n|<>".contains(c)) {
+                    delimiter.push(c);
+                }
+                if delimiter.is_empty() {
+                    return Err(ParseError::new(
+                        ErrorKind::MissingRedirectTarget("<<"),
+                        src,
+                        pos,
+                    ));
+                }
+                heredocs.push((tokens.len(), delimiter, pos));
+                tokens.push((pos, Token::Heredoc(String::new())));
+            }
+            // A `#` starting a word comments out the rest of the line.
+            '#' if word.is_none() => while chars.next_if(|&(_, c)| c != '\n').is_some() {},
+            '|' => {
+                if let Some((start, word)) = word.take() {
+                    tokens.push((start, Token::Word(word)));
+                }
+                match chars.next_if(|&(_, c)| c == '|') {
+                    Some(_) => tokens.push((pos, Token::Connector(Connector::Or))),
+                    None => tokens.push((pos, Token::Pipe)),
+                }
+            }
+            '&' if chars.peek().is_some_and(|&(_, c)| c == '&') => {
+                chars.next();
+                if let Some((start, word)) = word.take() {
+                    tokens.push((start, Token::Word(word)));
+                }
+                tokens.push((pos, Token::Connector(Connector::And)));
+            }
+            '>' => {
+                // A bare `2` right before the `>` picks stderr.
+                let mut start = pos;
+                let mut stderr = false;
+                match word.take() {
+                    Some((word_start, word)) if word.bare() == Some("2") => {
+                        start = word_start;
+                        stderr = true;
+                    }
+                    Some((start, word)) => tokens.push((start, Token::Word(word))),
+                    None => {}
+                }
+                let append = chars.next_if(|&(_, c)| c == '>').is_some();
+                tokens.push((start, Token::Output { append, stderr }));
+            }
+            '\\' => match chars.next() {
+                None => return Err(ParseError::new(ErrorKind::TrailingBackslash, src, pos)),
+                // A line continuation between words doesn't start one.
+                Some((_, '\n')) => {}
+                Some((_, c)) => {
+                    let word = &mut word.get_or_insert_with(|| (pos, Word::default())).1;
+                    word.quoted = true;
+                    word.push(c);
+                }
+            },
+            '$' => {
+                let word = &mut word.get_or_insert_with(|| (pos, Word::default())).1;
+                lex_dollar(src, pos, &mut chars, word)?;
+            }
+            '\'' => {
+                let word = &mut word.get_or_insert_with(|| (pos, Word::default())).1;
+                word.quoted = true;
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(ParseError::new(
+                                ErrorKind::UnterminatedQuote('\''),
+                                src,
+                                pos,
+                            ))
+                        }
+                        Some((_, '\'')) => break,
+                        Some((_, c)) => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = &mut word.get_or_insert_with(|| (pos, Word::default())).1;
+                word.quoted = true;
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(ParseError::new(
+                                ErrorKind::UnterminatedQuote('"'),
+                                src,
+                                pos,
+                            ))
+                        }
+                        Some((_, '"')) => break,
+                        Some((pos, '$')) => lex_dollar(src, pos, &mut chars, word)?,
+                        Some((_, '\\')) => match chars.peek() {
+                            Some((_, '\n')) => {
+                                chars.next();
+                            }
+                            Some(&(_, c @ ('"' | '\\' | '$' | '`'))) => {
+                                chars.next();
+                                word.push(c);
+                            }
+                            _ => word.push('\\'),
+                        },
+                        Some((_, c)) => word.push(c),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(|| (pos, Word::default())).1.push(c),
+        }
+    }
+    tokens.extend(word.map(|(start, word)| (start, Token::Word(word))));
+    if let Some((_, delimiter, pos)) = heredocs.pop() {
+        return Err(ParseError::new(
+            ErrorKind::UnterminatedHeredoc(delimiter),
+            src,
+            pos,
+        ));
+    }
+    Ok(tokens)
+}
+
+/// Reads lines up to and including one holding just `delimiter`, and
+/// returns the lines before it. `None` if the input ends first.
+fn lex_heredoc(chars: &mut Peekable<CharIndices>, delimiter: &str) -> Option<String> {
+    let mut body = String::new();
+    loop {
+        let mut line = String::new();
+        let mut ended = true;
+        for (_, c) in chars.by_ref() {
+            if c == '\n' {
+                ended = false;
+                break;
+            }
+            line.push(c);
+        }
+        if line == delimiter {
+            return Some(body);
+        }
+        if ended {
+            return None;
+        }
+        body.push_str(&line);
+        body.push('\n');
+    }
+}
+
+/// Lexes what follows a `$` at `pos`. A `$` that doesn't start an
+/// expansion is kept as a literal character. `?` is the one variable name
+/// [`is_name`] doesn't allow; it holds the last exit status.
+fn lex_dollar(
+    src: &str,
+    pos: usize,
+    chars: &mut Peekable<CharIndices>,
+    word: &mut Word,
+) -> Result<(), ParseError> {
+    if chars.next_if(|&(_, c)| c == '{').is_some() {
+        let mut name = String::new();
+        loop {
+            match chars.next() {
+                None => return Err(ParseError::new(ErrorKind::UnterminatedBrace, src, pos)),
+                Some((_, '}')) => break,
+                Some((_, c)) => name.push(c),
+            }
+        }
+        if name != "?" && !is_name(&name) {
+            return Err(ParseError::new(ErrorKind::BadVariableName(name), src, pos));
+        }
+        word.parts.push(Part::Var { name, pos });
+    } else if chars.next_if(|&(_, c)| c == '?').is_some() {
+        word.parts.push(Part::Var {
+            name: "?".to_string(),
+            pos,
+        });
+    } else if let Some((open, _)) = chars.next_if(|&(_, c)| c == '(') {
+        let mut depth = 1;
+        let close = loop {
+            let unterminated = || ParseError::new(ErrorKind::UnterminatedSubstitution, src, pos);
+            match chars.next().ok_or_else(unterminated)? {
+                (_, '\\') => {
+                    chars.next().ok_or_else(unterminated)?;
+                }
+                (_, '\'') => while chars.next().ok_or_else(unterminated)?.1 != '\'' {},
+                (_, '"') => loop {
+                    match chars.next().ok_or_else(unterminated)?.1 {
+                        '"' => break,
+                        '\\' => {
+                            chars.next().ok_or_else(unterminated)?;
+                        }
+                        _ => {}
+                    }
+                },
+                (_, '(') => depth += 1,
+                (close, ')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break close;
+                    }
+                }
+                _ => {}
+            }
+        };
+        word.parts.push(Part::Subst {
+            src: src[open + 1..close].to_string(),
+            pos,
+        });
+    } else if chars
+        .peek()
+        .is_some_and(|&(_, c)| c.is_ascii_alphabetic() || c == '_')
+    {
+        let mut name = String::new();
+        while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_') {
+            name.push(c);
+        }
+        word.parts.push(Part::Var { name, pos });
+    } else {
+        word.push('$');
+    }
+    Ok(())
+}
+
+/// A pipeline's input before expansion.
+enum StdinSpec {
+    File(Word),
+    Heredoc(String),
+}
+
+/// A pipeline before expansion: the words of each stage and the
+/// redirections with their positions.
+struct PipelineSpec {
+    stages: Vec<Vec<Word>>,
+    stdin: Option<StdinSpec>,
+    output: Option<(usize, Word, bool)>,
+    errors: Option<(usize, Word, bool)>,
+}
+
+impl PipelineSpec {
+    /// Whether this is `exit` on its own, with or without arguments.
+    fn is_exit(&self) -> bool {
+        match self.stages.as_slice() {
+            [words] => {
+                self.stdin.is_none()
+                    && self.output.is_none()
+                    && self.errors.is_none()
+                    && words[0].bare() == Some("exit")
+            }
+            _ => false,
+        }
+    }
+
+    fn expand(self, src: &str, env: &mut dyn Env) -> Result<Pipeline, ParseError> {
+        let mut commands = Vec::new();
+        for stage in self.stages {
+            let mut words = stage.into_iter();
+            let name = words.next().expect("stages are never empty");
+            commands.push(Command {
+                name: name.expand(src, env)?,
+                args: words
+                    .map(|word| word.expand(src, env))
+                    .collect::<Result<_, _>>()?,
+            });
+        }
+        let stdin = match self.stdin {
+            Some(StdinSpec::File(path)) => Some(Stdin::File(path.expand(src, env)?)),
+            Some(StdinSpec::Heredoc(body)) => Some(Stdin::Text(body)),
+            None => None,
+        };
+        let mut redirect = |target: Option<(usize, Word, bool)>| match target {
+            Some((_, path, append)) => Ok(Some(Redirect {
+                path: path.expand(src, env)?,
+                append,
+            })),
+            None => Ok(None),
+        };
+        Ok(Pipeline {
+            stdin,
+            output: redirect(self.output)?,
+            errors: redirect(self.errors)?,
+            commands,
+        })
+    }
+}
+
+/// Takes the file name after the redirection operator `op`.
+fn redirect_target(
+    src: &str,
+    tokens: &mut impl Iterator<Item = (usize, Token)>,
+    op: &'static str,
+) -> Result<Word, ParseError> {
+    match tokens.next() {
+        Some((_, Token::Word(path))) => Ok(path),
+        Some((pos, _)) => Err(ParseError::new(
+            ErrorKind::MissingRedirectTarget(op),
+            src,
+            pos,
+        )),
+        None => Err(ParseError::new(
+            ErrorKind::MissingRedirectTarget(op),
+            src,
+            src.len(),
+        )),
+    }
+}
+
+/// Parses `src` and expands every `$` in it. Expansion only starts once
+/// the whole input has parsed, so an error never leaves a substitution
+/// half-run. Pipelines joined by `&&` and `||` are left for the caller to
+/// expand one at a time, as [`Input::Chain`].
+pub fn parse(src: &str, env: &mut dyn Env) -> Result<Input, ParseError> {
+    let tokens = lex(src)?;
+
+    if let [(_, Token::Word(name)), (eq, Token::Word(op)), rest @ ..] = tokens.as_slice() {
+        if let (Some(name), Some("=")) = (name.bare().filter(|n| is_name(n)), op.bare()) {
+            return match rest {
+                [(_, Token::Word(value))] => {
+                    Ok(Input::Assign(name.to_string(), value.expand(src, env)?))
+                }
+                _ => Err(ParseError::new(ErrorKind::BadAssignment, src, *eq)),
+            };
+        }
+    }
+
+    // Split the tokens into pipelines at each `&&` and `||`.
+    let mut groups = vec![(None, Vec::new())];
+    for (pos, token) in tokens {
+        match token {
+            Token::Connector(connector) => groups.push((Some((pos, connector)), Vec::new())),
+            token => groups
+                .last_mut()
+                .expect("there is always a group")
+                .1
+                .push((pos, token)),
+        }
+    }
+    if groups.len() == 1 {
+        let (_, tokens) = groups.pop().expect("there is one group");
+        return match parse_pipeline(src, tokens, None)? {
+            None => Ok(Input::Empty),
+            Some(spec) if spec.is_exit() => {
+                let mut pipeline = spec.expand(src, env)?;
+                Ok(Input::Exit(pipeline.commands.remove(0).args))
+            }
+            Some(spec) => Ok(Input::Pipeline(spec.expand(src, env)?)),
+        };
+    }
+
+    let shared: Rc<str> = Rc::from(src);
+    let mut links = Vec::new();
+    let mut groups = groups.into_iter().peekable();
+    while let Some((after, tokens)) = groups.next() {
+        let next = groups
+            .peek()
+            .and_then(|(after, _)| after.map(|(pos, connector)| (pos, connector.as_str())));
+        let Some(spec) = parse_pipeline(src, tokens, next)? else {
+            // Only the last pipeline can be empty without an error.
+            let (pos, connector) = after.expect("a chain has a connector before its last pipeline");
+            let kind = ErrorKind::TrailingOperator(connector.as_str());
+            return Err(ParseError::new(kind, src, pos));
+        };
+        links.push(Link {
+            after: after.map(|(_, connector)| connector),
+            src: shared.clone(),
+            spec,
+        });
+    }
+    Ok(Input::Chain(links))
+}
+
+/// Parses one pipeline's tokens, without expanding them. `next` is the
+/// operator after the pipeline and its position, if there is one, for
+/// reporting a missing command. `None` if there are no tokens at all.
+fn parse_pipeline(
+    src: &str,
+    tokens: Vec<(usize, Token)>,
+    next: Option<(usize, &'static str)>,
+) -> Result<Option<PipelineSpec>, ParseError> {
+    let mut stages = Vec::new();
+    let mut words = Vec::new();
+    let mut output = None;
+    let mut errors = None;
+    let mut stdin = None;
+    // The first redirection and its operator, for errors.
+    let mut first_redirect = None;
+    let mut last_op = None;
+    let mut tokens = tokens.into_iter();
+    while let Some((pos, token)) = tokens.next() {
+        match token {
+            Token::Word(word) => words.push(word),
+            Token::Pipe => {
+                if output.is_some() || errors.is_some() {
+                    return Err(ParseError::new(ErrorKind::PipeAfterRedirect, src, pos));
+                }
+                if words.is_empty() {
+                    return Err(ParseError::new(ErrorKind::EmptyCommand("|"), src, pos));
+                }
+                stages.push(std::mem::take(&mut words));
+                last_op = Some(pos);
+            }
+            Token::Connector(_) => unreachable!("chains are split before pipelines are parsed"),
+            Token::Output { append, stderr } => {
+                let (target, stream) = match stderr {
+                    false => (&mut output, "output"),
+                    true => (&mut errors, "stderr"),
+                };
+                if target.is_some() {
+                    return Err(ParseError::new(
+                        ErrorKind::DuplicateRedirect(stream),
+                        src,
+                        pos,
+                    ));
+                }
+                let op = match (stderr, append) {
+                    (false, false) => ">",
+                    (false, true) => ">>",
+                    (true, false) => "2>",
+                    (true, true) => "2>>",
+                };
+                *target = Some((pos, redirect_target(src, &mut tokens, op)?, append));
+                first_redirect.get_or_insert((pos, op));
+            }
+            Token::Input | Token::Heredoc(_) => {
+                if !stages.is_empty() {
+                    return Err(ParseError::new(ErrorKind::InputAfterPipe, src, pos));
+                }
+                if stdin.is_some() {
+                    return Err(ParseError::new(
+                        ErrorKind::DuplicateRedirect("input"),
+                        src,
+                        pos,
+                    ));
+                }
+                let (spec, op) = match token {
+                    Token::Heredoc(body) => (StdinSpec::Heredoc(body), "<<"),
+                    _ => (
+                        StdinSpec::File(redirect_target(src, &mut tokens, "<")?),
+                        "<",
+                    ),
+                };
+                stdin = Some(spec);
+                first_redirect.get_or_insert((pos, op));
+            }
+        }
+    }
+    if words.is_empty() {
+        let (kind, pos) = match (first_redirect, next, last_op) {
+            (Some((pos, op)), _, _) | (None, Some((pos, op)), _) => {
+                (ErrorKind::EmptyCommand(op), pos)
+            }
+            (None, None, Some(pos)) => (ErrorKind::TrailingOperator("|"), pos),
+            (None, None, None) => return Ok(None),
+        };
+        return Err(ParseError::new(kind, src, pos));
+    }
+    stages.push(words);
+    Ok(Some(PipelineSpec {
+        stages,
+        stdin,
+        output,
+        errors,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An environment where `$x` is `1` and `$(...)` returns its source,
+    /// except that `$(fail)` fails and `$(ctrl-c)` is interrupted.
+    struct TestEnv;
+
+    impl Env for TestEnv {
+        fn var(&self, name: &str) -> Option<String> {
+            (name == "x").then(|| "1".to_string())
+        }
+
+        fn capture(&mut self, src: &str) -> anyhow::Result<String> {
+            match src {
+                "fail" => anyhow::bail!("failed"),
+                "ctrl-c" => Err(Interrupted::CtrlC.into()),
+                _ => Ok(src.to_string()),
+            }
+        }
+    }
+
+    /// The words of `src`, which must be a single command.
+    fn words(src: &str) -> Vec<String> {
+        match parse(src, &mut TestEnv) {
+            Ok(Input::Pipeline(pipeline)) => match pipeline.commands.as_slice() {
+                [Command { name, args }] => std::iter::once(name).chain(args).cloned().collect(),
+                _ => panic!("{src:?} is not a single command"),
+            },
+            Ok(_) => panic!("{src:?} is not a pipeline"),
+            Err(e) => panic!("{src:?} failed to parse: {e}"),
+        }
+    }
+
+    fn error(src: &str) -> ParseError {
+        match parse(src, &mut TestEnv) {
+            Err(e) => e,
+            Ok(_) => panic!("{src:?} parsed"),
+        }
+    }
+
+    #[test]
+    fn blanks_separate_words() {
+        assert_eq!(words("echo  a \t b\t"), ["echo", "a", "b"]);
+    }
+
+    #[test]
+    fn double_quotes_keep_whitespace() {
+        assert_eq!(words(r#"echo "a  b" "c	d""#), ["echo", "a  b", "c\td"]);
+        assert_eq!(words(r#"echo "" x"#), ["echo", "", "x"]);
+    }
+
+    #[test]
+    fn single_quotes_are_literal() {
+        assert_eq!(words(r#"echo 'a\ $x "b'"#), ["echo", r#"a\ $x "b"#]);
+    }
+
+    #[test]
+    fn double_quotes_allow_some_escapes() {
+        assert_eq!(
+            words(r#"echo "\" \\ \$x \n $x""#),
+            ["echo", r#"" \ $x \n 1"#]
+        );
+    }
+
+    #[test]
+    fn backslash_escapes_the_next_character() {
+        assert_eq!(
+            words(r"echo a\ b \'c\' \\ \$x"),
+            ["echo", "a b", "'c'", "\\", "$x"]
+        );
+    }
+
+    #[test]
+    fn quotes_join_with_adjacent_text() {
+        assert_eq!(words(r#"echo a'b c'"d e"f"#), ["echo", "ab cd ef"]);
+    }
+
+    #[test]
+    fn trailing_backslash_continues_the_line() {
+        assert_eq!(words("echo a\\\nb"), ["echo", "ab"]);
+        assert_eq!(words("echo a \\\n b"), ["echo", "a", "b"]);
+        assert_eq!(words("echo \"a\\\nb\""), ["echo", "ab"]);
+    }
+
+    #[test]
+    fn backslash_at_end_is_incomplete() {
+        let e = error("echo a\\");
+        assert!(matches!(e.kind, ErrorKind::TrailingBackslash));
+        assert!(e.is_incomplete());
+        assert_eq!((e.line, e.column), (1, 7));
+    }
+
+    #[test]
+    fn unterminated_quotes_are_incomplete() {
+        let e = error("echo 'a b");
+        assert!(matches!(e.kind, ErrorKind::UnterminatedQuote('\'')));
+        assert!(e.is_incomplete());
+        assert_eq!((e.line, e.column), (1, 6));
+
+        let e = error("echo a\n  \"b");
+        assert!(matches!(e.kind, ErrorKind::UnterminatedQuote('"')));
+        assert_eq!((e.line, e.column), (2, 3));
+    }
+
+    #[test]
+    fn errors_report_where_they_are() {
+        let e = error("| echo");
+        assert!(matches!(e.kind, ErrorKind::EmptyCommand("|")));
+        assert!(!e.is_incomplete());
+        assert_eq!(e.to_string(), "missing command before `|` at column 1");
+
+        let e = error("echo 'é' \\\n  $nope");
+        assert!(matches!(&e.kind, ErrorKind::UndefinedVariable(name) if name == "nope"));
+        assert_eq!(
+            e.to_string(),
+            "undefined variable `nope` at line 2, column 3"
+        );
+    }
+
+    #[test]
+    fn ctrl_c_in_a_substitution_is_not_a_failure() {
+        let e = error("echo $(fail)");
+        assert_eq!(e.to_string(), "substitution failed: failed at column 6");
+        let e = error("echo $(ctrl-c)");
+        assert!(matches!(e.kind, ErrorKind::Interrupted));
+        assert!(!e.is_incomplete());
+
+        // In a chain it happens when the pipeline is expanded.
+        let Ok(Input::Chain(links)) = parse("true && echo $(ctrl-c)", &mut TestEnv) else {
+            panic!("not a chain");
+        };
+        let mut links = links.into_iter();
+        assert!(links.next().unwrap().expand(&mut TestEnv).is_ok());
+        let e = links.next().unwrap().expand(&mut TestEnv).err().unwrap();
+        assert!(matches!(e.kind, ErrorKind::Interrupted));
+    }
+
+    #[test]
+    fn exit_keeps_its_arguments() {
+        assert!(matches!(parse("exit", &mut TestEnv), Ok(Input::Exit(args)) if args.is_empty()));
+        assert!(matches!(parse("exit $x", &mut TestEnv), Ok(Input::Exit(args)) if args == ["1"]));
+        // With a redirection it is an ordinary pipeline, which fails.
+        assert!(matches!(
+            parse("exit > f", &mut TestEnv),
+            Ok(Input::Pipeline(_))
+        ));
+    }
+
+    /// Keeps `$?` and records the substitutions it runs, to show when
+    /// expansion happens.
+    #[derive(Default)]
+    struct Recorder {
+        status: i32,
+        captured: Vec<String>,
+    }
+
+    impl Env for Recorder {
+        fn var(&self, name: &str) -> Option<String> {
+            (name == "?").then(|| self.status.to_string())
+        }
+
+        fn capture(&mut self, src: &str) -> anyhow::Result<String> {
+            self.captured.push(src.to_string());
+            Ok(src.to_uppercase())
+        }
+    }
+
+    fn chain(src: &str, env: &mut dyn Env) -> Vec<Link> {
+        match parse(src, env) {
+            Ok(Input::Chain(links)) => links,
+            Ok(_) => panic!("{src:?} is not a chain"),
+            Err(e) => panic!("{src:?} failed to parse: {e}"),
+        }
+    }
+
+    /// The names and arguments of each command in `pipeline`.
+    fn commands(pipeline: &Pipeline) -> Vec<Vec<String>> {
+        pipeline
+            .commands
+            .iter()
+            .map(|Command { name, args }| std::iter::once(name).chain(args).cloned().collect())
+            .collect()
+    }
+
+    #[test]
+    fn connectors_split_a_chain_into_pipelines() {
+        let links = chain("a | b x && c || d", &mut TestEnv);
+        let connectors: Vec<_> = links
+            .iter()
+            .map(|link| link.after.map(Connector::as_str))
+            .collect();
+        assert_eq!(connectors, [None, Some("&&"), Some("||")]);
+        let pipelines: Vec<_> = links
+            .into_iter()
+            .map(|link| commands(&link.expand(&mut TestEnv).unwrap()))
+            .collect();
+        assert_eq!(
+            pipelines,
+            [
+                vec![vec!["a"], vec!["b", "x"]],
+                vec![vec!["c"]],
+                vec![vec!["d"]]
+            ]
+        );
+        // Without blanks too, while a lone `&` is an ordinary character.
+        assert_eq!(chain("a&&b&c||d", &mut TestEnv).len(), 3);
+    }
+
+    #[test]
+    fn chain_links_expand_only_when_asked() {
+        let mut env = Recorder::default();
+        let links = chain("a $(one) && b $? $(two)", &mut env);
+        assert!(env.captured.is_empty());
+        let mut links = links.into_iter();
+        let first = links.next().unwrap().expand(&mut env).unwrap();
+        assert_eq!(commands(&first), [["a", "ONE"]]);
+        assert_eq!(env.captured, ["one"]);
+        env.status = 3;
+        let second = links.next().unwrap().expand(&mut env).unwrap();
+        assert_eq!(commands(&second), [["b", "3", "TWO"]]);
+        assert_eq!(env.captured, ["one", "two"]);
+    }
+
+    #[test]
+    fn a_lone_pipeline_expands_right_away() {
+        let mut env = Recorder {
+            status: 2,
+            ..Recorder::default()
+        };
+        let Ok(Input::Pipeline(pipeline)) = parse("a $? $(b) ${?}", &mut env) else {
+            panic!("not a pipeline");
+        };
+        assert_eq!(commands(&pipeline), [["a", "2", "B", "2"]]);
+        assert_eq!(env.captured, ["b"]);
+    }
+
+    #[test]
+    fn exit_can_end_a_chain() {
+        let links = chain("a || exit 2", &mut TestEnv);
+        assert!(!links[0].is_exit());
+        assert!(links[1].is_exit());
+        // Not when it is part of a pipeline or redirected.
+        assert!(!chain("a || exit | b", &mut TestEnv)[1].is_exit());
+        assert!(!chain("a || exit > f", &mut TestEnv)[1].is_exit());
+    }
+
+    #[test]
+    fn a_trailing_operator_continues_on_the_next_line() {
+        for (src, op, column) in [("a &&", "&&", 3), ("a ||  ", "||", 3), ("a | b |", "|", 7)] {
+            let e = error(src);
+            assert!(
+                matches!(e.kind, ErrorKind::TrailingOperator(o) if o == op),
+                "{src:?}"
+            );
+            assert!(e.is_incomplete());
+            assert_eq!(e.column, column, "{src:?}");
+        }
+        assert_eq!(chain("a &&\n  b ||\nc", &mut TestEnv).len(), 3);
+        let Ok(Input::Pipeline(pipeline)) = parse("a |\n b", &mut TestEnv) else {
+            panic!("not a pipeline");
+        };
+        assert_eq!(commands(&pipeline), [["a"], ["b"]]);
+    }
+
+    #[test]
+    fn connectors_need_a_command_before_them() {
+        for (src, op, column) in [
+            ("&& b", "&&", 1),
+            ("a && || b", "||", 6),
+            ("a | && b", "&&", 5),
+        ] {
+            let e = error(src);
+            assert!(
+                matches!(e.kind, ErrorKind::EmptyCommand(o) if o == op),
+                "{src:?}"
+            );
+            assert!(!e.is_incomplete());
+            assert_eq!(e.column, column, "{src:?}");
+        }
+    }
+
+    fn pipeline(src: &str) -> Pipeline {
+        match parse(src, &mut TestEnv) {
+            Ok(Input::Pipeline(pipeline)) => pipeline,
+            Ok(_) => panic!("{src:?} is not a pipeline"),
+            Err(e) => panic!("{src:?} failed to parse: {e}"),
+        }
+    }
+
+    /// The heredoc body `src` gives its pipeline.
+    fn heredoc(src: &str) -> String {
+        match pipeline(src).stdin {
+            Some(Stdin::Text(text)) => text,
+            _ => panic!("{src:?} has no heredoc"),
+        }
+    }
+
+    #[test]
+    fn heredocs_take_the_following_lines_literally() {
+        assert_eq!(
+            heredoc("cat <<EOF\none\n  two $x \\\nEOF"),
+            "one\n  two $x \\\n"
+        );
+        assert_eq!(heredoc("cat << END\nEND"), "");
+        let pipeline = pipeline("cat <<EOF | wc\na\nEOF");
+        assert_eq!(commands(&pipeline), [["cat"], ["wc"]]);
+        assert!(matches!(pipeline.stdin, Some(Stdin::Text(text)) if text == "a\n"));
+    }
+
+    #[test]
+    fn heredocs_end_at_a_line_of_just_the_delimiter() {
+        assert_eq!(
+            heredoc("cat <<EOF\n EOF\nEOF x\neof\nEOF\n"),
+            " EOF\nEOF x\neof\n"
+        );
+        // What follows the delimiter line is the rest of the input.
+        let links = chain("cat <<EOF &&\nbody\nEOF\necho $x", &mut TestEnv);
+        let pipelines: Vec<_> = links
+            .into_iter()
+            .map(|link| link.expand(&mut TestEnv).unwrap())
+            .collect();
+        assert!(matches!(&pipelines[0].stdin, Some(Stdin::Text(text)) if text == "body\n"));
+        assert_eq!(commands(&pipelines[1]), [["echo", "1"]]);
+    }
+
+    #[test]
+    fn a_heredoc_without_its_delimiter_is_incomplete() {
+        for src in ["cat <<EOF", "cat <<EOF\none", "cat <<EOF\none\nEO"] {
+            let e = error(src);
+            assert!(
+                matches!(&e.kind, ErrorKind::UnterminatedHeredoc(d) if d == "EOF"),
+                "{src:?}"
+            );
+            assert!(e.is_incomplete());
+            assert_eq!((e.line, e.column), (1, 5), "{src:?}");
+            assert_eq!(
+                e.to_string(),
+                "heredoc is missing its `EOF` line at column 5"
+            );
+        }
+        let e = error("cat <<");
+        assert!(matches!(e.kind, ErrorKind::MissingRedirectTarget("<<")));
+        assert!(!e.is_incomplete());
+    }
+
+    #[test]
+    fn input_comes_from_a_file() {
+        assert!(
+            matches!(pipeline("cat < in.txt").stdin, Some(Stdin::File(path)) if path == "in.txt")
+        );
+        assert!(
+            matches!(pipeline("cat <$x.txt").stdin, Some(Stdin::File(path)) if path == "1.txt")
+        );
+        let e = error("cat <");
+        assert!(matches!(e.kind, ErrorKind::MissingRedirectTarget("<")));
+    }
+
+    #[test]
+    fn only_the_first_command_reads_input_once() {
+        for (src, column) in [("a | b < f", 7), ("a | b <<EOF\nx\nEOF", 7)] {
+            let e = error(src);
+            assert!(matches!(e.kind, ErrorKind::InputAfterPipe), "{src:?}");
+            assert_eq!(e.column, column, "{src:?}");
+        }
+        for (src, column) in [
+            ("a < f < g", 7),
+            ("a < f <<EOF\nx\nEOF", 7),
+            ("a <<A <<B\nA\nB", 7),
+        ] {
+            let e = error(src);
+            assert!(
+                matches!(e.kind, ErrorKind::DuplicateRedirect("input")),
+                "{src:?}"
+            );
+            assert_eq!(e.column, column, "{src:?}");
+            assert_eq!(
+                e.to_string(),
+                format!("input is already redirected at column {column}")
+            );
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::config::Preopen;
+use crate::grants::Grants;
+
+/// Fewest hex digits of a digest that a `[hashes]` entry may use.
+const MIN_HASH_PREFIX: usize = 12;
+
+/// On-disk layout of a policy file.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyFile {
+    default: Option<EntryFile>,
+    commands: BTreeMap<String, EntryFile>,
+    hashes: BTreeMap<String, EntryFile>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct EntryFile {
+    read: Vec<String>,
+    write: Vec<String>,
+    env: BTreeMap<String, String>,
+    stdio: Option<bool>,
+    clocks: bool,
+    random: bool,
+    network: bool,
+}
+
+impl EntryFile {
+    /// Resolves relative directories against `base`. Anything the entry
+    /// leaves out is denied, except stdio.
+    fn into_grants(self, base: &Path) -> Grants {
+        let read = self
+            .read
+            .iter()
+            .map(|spec| (Preopen::parse(spec, base), false));
+        let write = self
+            .write
+            .iter()
+            .map(|spec| (Preopen::parse(spec, base), true));
+        Grants {
+            dirs: read.chain(write).collect(),
+            env: self.env.into_iter().collect(),
+            stdio: self.stdio.unwrap_or(true),
+            clocks: self.clocks,
+            random: self.random,
+            network: self.network,
+        }
+    }
+}
+
+/// The grants each command gets, by the digest of its bytecode or by
+/// name. An entry replaces the default entirely rather than adding to it,
+/// so every entry can be reviewed on its own.
+#[derive(Debug, Default)]
+pub struct Policy {
+    /// The file this was read from, if any.
+    pub path: Option<PathBuf>,
+    default: Grants,
+    commands: BTreeMap<String, Grants>,
+    /// Lowercase hex digest prefixes.
+    hashes: BTreeMap<String, Grants>,
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let file: PolicyFile =
+            toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mut hashes = BTreeMap::new();
+        for (hash, entry) in file.hashes {
+            let valid = (MIN_HASH_PREFIX..=64).contains(&hash.len())
+                && hash.chars().all(|c| c.is_ascii_hexdigit());
+            if !valid {
+                anyhow::bail!(
+                    "{}: `{hash}` is not a SHA-256 digest or a prefix of at least {MIN_HASH_PREFIX} hex digits",
+                    path.display()
+                );
+            }
+            let key = hash.to_ascii_lowercase();
+            if hashes.insert(key, entry.into_grants(base)).is_some() {
+                anyhow::bail!("{}: `{hash}` is listed twice", path.display());
+            }
+        }
+        // A prefix sorts right before the keys it is a prefix of, so
+        // checking neighbours finds every overlap.
+        let keys: Vec<&String> = hashes.keys().collect();
+        for pair in keys.windows(2) {
+            if pair[1].starts_with(pair[0].as_str()) {
+                anyhow::bail!(
+                    "{}: `{}` and `{}` can both match the same digest",
+                    path.display(),
+                    pair[0],
+                    pair[1]
+                );
+            }
+        }
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            default: file.default.unwrap_or_default().into_grants(base),
+            commands: file
+                .commands
+                .into_iter()
+                .map(|(name, entry)| (name, entry.into_grants(base)))
+                .collect(),
+            hashes,
+        })
+    }
+
+    /// What commands without an entry of their own get.
+    pub fn default_grants(&self) -> &Grants {
+        &self.default
+    }
+
+    /// The grants for `cmd` with bytecode digest `digest`, and the entry
+    /// they came from. A matching hash entry wins over a name entry.
+    pub fn for_command(&self, cmd: &str, digest: &[u8; 32]) -> (&Grants, String) {
+        let hex = crate::cache::hex(digest);
+        if let Some((hash, grants)) = self.hashes.iter().find(|(hash, _)| hex.starts_with(*hash)) {
+            return (grants, format!("[hashes.\"{hash}\"]"));
+        }
+        match self.commands.get(cmd) {
+            Some(grants) => (grants, format!("[commands.\"{cmd}\"]")),
+            None if self.path.is_some() => (&self.default, "[default]".to_string()),
+            None => (&self.default, "built-in default".to_string()),
+        }
+    }
+}
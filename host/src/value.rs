@@ -0,0 +1,337 @@
+use std::fmt;
+
+use crate::bindings::structured::exports::wasi_repl::host::structured::Node;
+
+/// How deeply values from commands may nest, so that a malicious one
+/// can't exhaust the host's stack.
+const MAX_DEPTH: usize = 100;
+
+/// What a pipeline stage produces. Text commands and builtins produce
+/// strings; structured commands can produce anything.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    S64(i64),
+    F64(f64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Record(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Rebuilds a value from the nodes a structured command returned,
+    /// checking that they form a tree rooted at the first node.
+    pub fn from_nodes(nodes: Vec<Node>) -> Result<Self, String> {
+        if nodes.is_empty() {
+            return Err("a value needs at least one node".to_string());
+        }
+        let mut nodes: Vec<Option<Node>> = nodes.into_iter().map(Some).collect();
+        let value = Self::take_node(&mut nodes, 0, 0)?;
+        // Whatever the walk didn't reach belongs to no node.
+        if let Some(index) = nodes.iter().position(Option::is_some) {
+            return Err(format!("node {index} is not an element of any node"));
+        }
+        Ok(value)
+    }
+
+    /// Removes node `index` from `nodes` along with its elements, so that
+    /// a node used twice is reported rather than copied.
+    fn take_node(nodes: &mut [Option<Node>], index: usize, depth: usize) -> Result<Self, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("values can't nest more than {MAX_DEPTH} deep"));
+        }
+        let node = nodes[index]
+            .take()
+            .ok_or_else(|| format!("node {index} is used more than once"))?;
+        let mut element = |child: u32| {
+            let child = child as usize;
+            if child <= index || child >= nodes.len() {
+                return Err(format!("node {index} refers to node {child}"));
+            }
+            Self::take_node(nodes, child, depth + 1)
+        };
+        Ok(match node {
+            Node::String(s) => Value::String(s),
+            Node::S64(n) => Value::S64(n),
+            Node::F64(x) => Value::F64(x),
+            Node::Bool(b) => Value::Bool(b),
+            Node::Bytes(bytes) => Value::Bytes(bytes),
+            Node::List(items) => Value::List(
+                items
+                    .into_iter()
+                    .map(&mut element)
+                    .collect::<Result<_, _>>()?,
+            ),
+            Node::Record(fields) => Value::Record(
+                fields
+                    .into_iter()
+                    .map(|(name, child)| Ok((name, element(child)?)))
+                    .collect::<Result<_, String>>()?,
+            ),
+        })
+    }
+
+    /// Flattens the value into nodes for a structured command, the root
+    /// first.
+    pub fn to_nodes(&self) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        self.push_node(&mut nodes);
+        nodes
+    }
+
+    /// Appends this value's node, then its elements', and returns the
+    /// index of its own.
+    fn push_node(&self, nodes: &mut Vec<Node>) -> u32 {
+        let index = nodes.len();
+        nodes.push(Node::Bool(false));
+        nodes[index] = match self {
+            Value::String(s) => Node::String(s.clone()),
+            Value::S64(n) => Node::S64(*n),
+            Value::F64(x) => Node::F64(*x),
+            Value::Bool(b) => Node::Bool(*b),
+            Value::Bytes(bytes) => Node::Bytes(bytes.clone()),
+            Value::List(items) => {
+                Node::List(items.iter().map(|item| item.push_node(nodes)).collect())
+            }
+            Value::Record(fields) => Node::Record(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.push_node(nodes)))
+                    .collect(),
+            ),
+        };
+        index as u32
+    }
+
+    /// The value on one line, for a table cell or inside another value.
+    fn inline(&self) -> String {
+        match self {
+            Value::List(items) => {
+                let items: Vec<_> = items.iter().map(Value::inline).collect();
+                format!("[{}]", items.join(", "))
+            }
+            Value::Record(fields) => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{name}: {}", value.inline()))
+                    .collect();
+                format!("{{{}}}", fields.join(", "))
+            }
+            value => value.to_string(),
+        }
+    }
+}
+
+/// Renders the value as the REPL prints it. A string is printed as it is,
+/// a record as one field per line, a list of records as a table and any
+/// other list as one element per line. Bytes are printed as text if they
+/// are UTF-8, and in hex otherwise.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => f.write_str(s),
+            Value::S64(n) => write!(f, "{n}"),
+            Value::F64(x) => write!(f, "{x:?}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Bytes(bytes) => match std::str::from_utf8(bytes) {
+                Ok(text) => f.write_str(text),
+                Err(_) => {
+                    let hex: Vec<_> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+                    f.write_str(&hex.join(" "))
+                }
+            },
+            Value::Record(fields) => {
+                let width = fields.iter().map(|(name, _)| name.chars().count()).max();
+                let lines: Vec<_> = fields
+                    .iter()
+                    .map(|(name, value)| {
+                        let width = width.unwrap_or(0);
+                        format!("{name:<width$}  {}", value.inline())
+                    })
+                    .collect();
+                f.write_str(&lines.join("\n"))
+            }
+            Value::List(items) if !items.is_empty() && items.iter().all(is_record) => {
+                f.write_str(&table(items))
+            }
+            Value::List(items) => {
+                let lines: Vec<_> = items.iter().map(Value::inline).collect();
+                f.write_str(&lines.join("\n"))
+            }
+        }
+    }
+}
+
+fn is_record(value: &Value) -> bool {
+    matches!(value, Value::Record(_))
+}
+
+/// Lays out records as a table, with a column for every field name in
+/// the order they first appear. Fields a record doesn't have are blank.
+fn table(rows: &[Value]) -> String {
+    let mut columns: Vec<&str> = Vec::new();
+    for row in rows {
+        if let Value::Record(fields) = row {
+            for (name, _) in fields {
+                if !columns.contains(&name.as_str()) {
+                    columns.push(name);
+                }
+            }
+        }
+    }
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            let Value::Record(fields) = row else {
+                unreachable!("tables are made of records")
+            };
+            columns
+                .iter()
+                .map(|column| {
+                    let field = fields.iter().find(|(name, _)| name == column);
+                    field.map_or(String::new(), |(_, value)| value.inline())
+                })
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let cells = cells.iter().map(|row| row[i].chars().count());
+            cells.chain([column.chars().count()]).max().unwrap_or(0)
+        })
+        .collect();
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<_> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{cell:<width$}"))
+            .collect();
+        padded.join("  ").trim_end().to_string()
+    };
+    let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+    let mut lines = vec![
+        line(columns.clone()),
+        line(rule.iter().map(String::as_str).collect()),
+    ];
+    lines.extend(
+        cells
+            .iter()
+            .map(|row| line(row.iter().map(String::as_str).collect())),
+    );
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(nodes: Vec<Node>) -> String {
+        match Value::from_nodes(nodes) {
+            Err(e) => e,
+            Ok(value) => panic!("accepted as {value:?}"),
+        }
+    }
+
+    fn record(fields: &[(&str, Value)]) -> Value {
+        Value::Record(
+            fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn nodes_round_trip() {
+        let value = Value::List(vec![
+            record(&[("name", Value::String("a".into())), ("size", Value::S64(1))]),
+            Value::Bytes(vec![0, 255]),
+            Value::List(vec![Value::F64(0.5), Value::Bool(true)]),
+        ]);
+        assert_eq!(Value::from_nodes(value.to_nodes()), Ok(value));
+    }
+
+    #[test]
+    fn malformed_nodes_are_rejected() {
+        assert_eq!(error(vec![]), "a value needs at least one node");
+        assert_eq!(
+            error(vec![Node::S64(1), Node::S64(2)]),
+            "node 1 is not an element of any node"
+        );
+        assert_eq!(
+            error(vec![Node::List(vec![1, 1]), Node::S64(2)]),
+            "node 1 is used more than once"
+        );
+        assert_eq!(
+            error(vec![
+                Node::List(vec![1, 2]),
+                Node::Record(vec![("a".into(), 2)]),
+                Node::S64(2),
+            ]),
+            "node 2 is used more than once"
+        );
+        assert_eq!(error(vec![Node::List(vec![0])]), "node 0 refers to node 0");
+        assert_eq!(
+            error(vec![Node::List(vec![1]), Node::List(vec![0])]),
+            "node 1 refers to node 0"
+        );
+        assert_eq!(error(vec![Node::List(vec![5])]), "node 0 refers to node 5");
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        // A list in a list in a list..., `depth` lists deep.
+        let nested = |depth: u32| {
+            let mut nodes: Vec<Node> = (1..=depth).map(|i| Node::List(vec![i])).collect();
+            nodes.push(Node::S64(0));
+            nodes
+        };
+        assert!(Value::from_nodes(nested(MAX_DEPTH as u32)).is_ok());
+        assert_eq!(
+            error(nested(MAX_DEPTH as u32 + 1)),
+            format!("values can't nest more than {MAX_DEPTH} deep")
+        );
+    }
+
+    #[test]
+    fn records_print_one_field_per_line() {
+        let value = record(&[
+            ("name", Value::String("x".into())),
+            ("sizes", Value::List(vec![Value::S64(1), Value::S64(2)])),
+            ("ok", Value::Bool(true)),
+        ]);
+        assert_eq!(value.to_string(), "name   x\nsizes  [1, 2]\nok     true");
+    }
+
+    #[test]
+    fn lists_of_records_print_as_tables() {
+        let value = Value::List(vec![
+            record(&[("name", Value::String("a".into())), ("size", Value::S64(1))]),
+            record(&[
+                ("name", Value::String("bb".into())),
+                ("size", Value::S64(22)),
+                ("ok", Value::Bool(true)),
+            ]),
+            record(&[("ok", Value::Bool(false))]),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            "name  size  ok\n----  ----  -----\na     1\nbb    22    true\n            false"
+        );
+    }
+
+    #[test]
+    fn other_values_print_plainly() {
+        let list = Value::List(vec![
+            Value::F64(1.0),
+            record(&[("a", Value::S64(1))]),
+            Value::List(vec![]),
+        ]);
+        assert_eq!(list.to_string(), "1.0\n{a: 1}\n[]");
+        assert_eq!(Value::Bytes(b"text".to_vec()).to_string(), "text");
+        assert_eq!(Value::Bytes(vec![0xff, 0x00]).to_string(), "ff 00");
+    }
+}
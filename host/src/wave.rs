@@ -0,0 +1,773 @@
+//! Component values as text, in the WebAssembly Value Encoding (WAVE):
+//! `true`, `42`, `1.5`, `'c'`, `"text"`, `[1, 2]`, `(1, "a")`,
+//! `{name: "x", size: 3}`, `case(payload)`, `some(x)`, `none`, `ok(x)`,
+//! `err(e)` and `{flag-a, flag-b}`. Labels that would read as keywords are
+//! written with a leading `%`.
+
+use std::fmt::Write;
+
+use anyhow::Context;
+use wasmtime::component::{Type, Val};
+
+/// Labels that need a `%` so they aren't read as values.
+const KEYWORDS: &[&str] = &["true", "false", "some", "none", "ok", "err", "inf", "nan"];
+
+/// Parses `src` as a value of type `ty`.
+fn parse(ty: &Type, src: &str) -> anyhow::Result<Val> {
+    let mut parser = Parser { src, pos: 0 };
+    let val = parser.value(ty)?;
+    parser.skip_blanks();
+    if parser.pos < src.len() {
+        parser.fail("unexpected text after the value")?;
+    }
+    Ok(val)
+}
+
+/// Parses `src` as the argument at `index`, of type `ty`. A string argument
+/// that isn't in double quotes is taken as it is, so that text doesn't
+/// need two levels of quoting at the prompt.
+pub fn parse_arg(ty: &Type, index: usize, src: &str) -> anyhow::Result<Val> {
+    if matches!(ty, Type::String) && !src.trim_start().starts_with('"') {
+        return Ok(Val::String(src.into()));
+    }
+    parse(ty, src).with_context(|| format!("argument {}", index + 1))
+}
+
+/// `val` as WAVE text.
+pub fn print(val: &Val) -> String {
+    let mut out = String::new();
+    write_val(&mut out, val);
+    out
+}
+
+/// A short name for `ty`, like WIT's, for error messages.
+pub fn describe(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        Type::S8 => "s8".to_string(),
+        Type::U8 => "u8".to_string(),
+        Type::S16 => "s16".to_string(),
+        Type::U16 => "u16".to_string(),
+        Type::S32 => "s32".to_string(),
+        Type::U32 => "u32".to_string(),
+        Type::S64 => "s64".to_string(),
+        Type::U64 => "u64".to_string(),
+        Type::Float32 => "f32".to_string(),
+        Type::Float64 => "f64".to_string(),
+        Type::Char => "char".to_string(),
+        Type::String => "string".to_string(),
+        Type::List(list) => format!("list<{}>", describe(&list.ty())),
+        Type::Tuple(tuple) => {
+            let types: Vec<_> = tuple.types().map(|ty| describe(&ty)).collect();
+            format!("tuple<{}>", types.join(", "))
+        }
+        Type::Option(option) => format!("option<{}>", describe(&option.ty())),
+        Type::Result(result) => {
+            let describe = |ty: Option<Type>| ty.map_or("_".to_string(), |ty| describe(&ty));
+            format!(
+                "result<{}, {}>",
+                describe(result.ok()),
+                describe(result.err())
+            )
+        }
+        Type::Record(record) => {
+            let fields: Vec<_> = record.fields().map(|field| field.name).collect();
+            format!("record {{{}}}", fields.join(", "))
+        }
+        Type::Variant(variant) => {
+            let cases: Vec<_> = variant.cases().map(|case| case.name).collect();
+            format!("variant {{{}}}", cases.join(", "))
+        }
+        Type::Enum(enum_) => {
+            let names: Vec<_> = enum_.names().collect();
+            format!("enum {{{}}}", names.join(", "))
+        }
+        Type::Flags(flags) => {
+            let names: Vec<_> = flags.names().collect();
+            format!("flags {{{}}}", names.join(", "))
+        }
+        Type::Own(_) | Type::Borrow(_) => "resource".to_string(),
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn fail<T>(&self, msg: &str) -> anyhow::Result<T> {
+        anyhow::bail!(
+            "{msg} at column {}",
+            self.src[..self.pos].chars().count() + 1
+        )
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_blanks(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// Skips blanks, then consumes `c` if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_blanks();
+        if self.peek() == Some(c) {
+            self.bump();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, c: char) -> anyhow::Result<()> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => self.fail(&format!("expected `{c}`")),
+        }
+    }
+
+    /// A run of characters that can make up a label or a number.
+    fn token(&mut self) -> &str {
+        self.skip_blanks();
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || "-+._%".contains(c))
+        {
+            self.bump();
+        }
+        &self.src[start..self.pos]
+    }
+
+    /// A label, without the `%` that may escape it.
+    fn label(&mut self) -> anyhow::Result<String> {
+        let start = self.pos;
+        let token = self.token();
+        if token.is_empty() {
+            self.pos = start;
+            return self.fail("expected a name");
+        }
+        Ok(token.strip_prefix('%').unwrap_or(token).to_string())
+    }
+
+    /// Parses the comma-separated items between `open` and `close`,
+    /// allowing a trailing comma.
+    fn items<T>(
+        &mut self,
+        open: char,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> anyhow::Result<T>,
+    ) -> anyhow::Result<Vec<T>> {
+        self.expect(open)?;
+        let mut items = Vec::new();
+        loop {
+            if self.eat(close) {
+                return Ok(items);
+            }
+            items.push(item(self)?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                return Ok(items);
+            }
+        }
+    }
+
+    /// The `(payload)` after a case name, if its type has one.
+    fn payload(&mut self, ty: Option<Type>) -> anyhow::Result<Option<Val>> {
+        let Some(ty) = ty else {
+            return Ok(None);
+        };
+        self.expect('(')?;
+        let val = self.value(&ty)?;
+        self.expect(')')?;
+        Ok(Some(val))
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, ty: &Type) -> anyhow::Result<T> {
+        let start = self.pos;
+        let token = self.token();
+        match token.parse() {
+            Ok(n) => Ok(n),
+            Err(_) => {
+                self.pos = start;
+                self.fail(&format!("expected {}", describe(ty)))
+            }
+        }
+    }
+
+    /// An `f32` or `f64`, parsed at that width so it rounds only once.
+    fn float<T: std::str::FromStr + From<f32>>(&mut self) -> anyhow::Result<T> {
+        let start = self.pos;
+        match self.token() {
+            "nan" => Ok(f32::NAN.into()),
+            "inf" => Ok(f32::INFINITY.into()),
+            "-inf" => Ok(f32::NEG_INFINITY.into()),
+            token => match token.parse() {
+                Ok(x) => Ok(x),
+                Err(_) => {
+                    self.pos = start;
+                    self.fail("expected a number")
+                }
+            },
+        }
+    }
+
+    /// The text between `quote`s, with escapes resolved.
+    fn quoted(&mut self, quote: char) -> anyhow::Result<String> {
+        self.expect(quote)?;
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                None => return self.fail(&format!("missing closing `{quote}`")),
+                Some(c) if c == quote => return Ok(text),
+                Some('\\') => text.push(self.escape()?),
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> anyhow::Result<char> {
+        match self.bump() {
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some(c @ ('\\' | '\'' | '"')) => Ok(c),
+            Some('u') => {
+                self.expect('{')?;
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+                    self.bump();
+                }
+                let code = u32::from_str_radix(&self.src[start..self.pos], 16).ok();
+                self.expect('}')?;
+                match code.and_then(char::from_u32) {
+                    Some(c) => Ok(c),
+                    None => self.fail("bad `\\u{...}` escape"),
+                }
+            }
+            _ => self.fail("unknown escape"),
+        }
+    }
+
+    fn value(&mut self, ty: &Type) -> anyhow::Result<Val> {
+        self.skip_blanks();
+        Ok(match ty {
+            Type::Bool => match self.token() {
+                "true" => Val::Bool(true),
+                "false" => Val::Bool(false),
+                _ => return self.fail("expected `true` or `false`"),
+            },
+            Type::S8 => Val::S8(self.number(ty)?),
+            Type::U8 => Val::U8(self.number(ty)?),
+            Type::S16 => Val::S16(self.number(ty)?),
+            Type::U16 => Val::U16(self.number(ty)?),
+            Type::S32 => Val::S32(self.number(ty)?),
+            Type::U32 => Val::U32(self.number(ty)?),
+            Type::S64 => Val::S64(self.number(ty)?),
+            Type::U64 => Val::U64(self.number(ty)?),
+            Type::Float32 => Val::Float32(self.float()?),
+            Type::Float64 => Val::Float64(self.float()?),
+            Type::Char => {
+                let text = self.quoted('\'')?;
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Val::Char(c),
+                    _ => return self.fail("expected one character"),
+                }
+            }
+            Type::String => Val::String(self.quoted('"')?.into()),
+            Type::List(list) => {
+                let element = list.ty();
+                let values = self.items('[', ']', |p| p.value(&element))?;
+                list.new_val(values.into())?
+            }
+            Type::Tuple(tuple) => {
+                let mut types = tuple.types();
+                let values = self.items('(', ')', |p| match types.next() {
+                    Some(ty) => p.value(&ty),
+                    None => p.fail("too many tuple elements"),
+                })?;
+                if values.len() != tuple.types().len() {
+                    return self.fail(&format!("expected {}", describe(ty)));
+                }
+                tuple.new_val(values.into())?
+            }
+            Type::Record(record) => {
+                let mut seen = Vec::new();
+                let mut given = self.items('{', '}', |p| {
+                    let name = p.label()?;
+                    let Some(field) = record.fields().find(|field| field.name == name) else {
+                        return p.fail(&format!("no field `{name}`"));
+                    };
+                    if seen.contains(&name) {
+                        return p.fail(&format!("field `{name}` given twice"));
+                    }
+                    seen.push(name.clone());
+                    p.expect(':')?;
+                    Ok((name, p.value(&field.ty)?))
+                })?;
+                // Optional fields may be left out.
+                let mut values = Vec::new();
+                for field in record.fields() {
+                    match given.iter().position(|(name, _)| name == field.name) {
+                        Some(i) => values.push((field.name, given.swap_remove(i).1)),
+                        None => match &field.ty {
+                            Type::Option(option) => {
+                                values.push((field.name, option.new_val(None)?))
+                            }
+                            _ => return self.fail(&format!("missing field `{}`", field.name)),
+                        },
+                    }
+                }
+                record.new_val(values)?
+            }
+            Type::Variant(variant) => {
+                let name = self.label()?;
+                let Some(case) = variant.cases().find(|case| case.name == name) else {
+                    return self.fail(&format!("expected {}", describe(ty)));
+                };
+                let payload = self.payload(case.ty)?;
+                variant.new_val(&name, payload)?
+            }
+            Type::Enum(enum_) => {
+                let name = self.label()?;
+                enum_
+                    .new_val(&name)
+                    .or_else(|_| self.fail(&format!("expected {}", describe(ty))))?
+            }
+            Type::Option(option) => match self.token() {
+                "none" => option.new_val(None)?,
+                "some" => {
+                    let payload = self.payload(Some(option.ty()))?;
+                    option.new_val(payload)?
+                }
+                _ => return self.fail("expected `some(...)` or `none`"),
+            },
+            Type::Result(result) => match self.token() {
+                "ok" => {
+                    let payload = self.payload(result.ok())?;
+                    result.new_val(Ok(payload))?
+                }
+                "err" => {
+                    let payload = self.payload(result.err())?;
+                    result.new_val(Err(payload))?
+                }
+                _ => return self.fail("expected `ok` or `err`"),
+            },
+            Type::Flags(flags) => {
+                let names = self.items('{', '}', Self::label)?;
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                flags
+                    .new_val(&names)
+                    .or_else(|_| self.fail(&format!("expected {}", describe(ty))))?
+            }
+            Type::Own(_) | Type::Borrow(_) => {
+                return self.fail("resources can't be written as values")
+            }
+        })
+    }
+}
+
+/// `name` with a `%` if it would read as a keyword.
+fn label(name: &str) -> String {
+    match KEYWORDS.contains(&name) {
+        true => format!("%{name}"),
+        false => name.to_string(),
+    }
+}
+
+/// Writes `x` at its own width, so an `f32` comes out as the shortest
+/// text that reads back as the same `f32`.
+fn write_float<T: std::fmt::Display + Into<f64> + Copy>(out: &mut String, x: T) {
+    let wide: f64 = x.into();
+    if wide.is_nan() {
+        out.push_str("nan");
+    } else if wide.is_infinite() {
+        out.push_str(if wide > 0.0 { "inf" } else { "-inf" });
+    } else {
+        let _ = write!(out, "{x}");
+    }
+}
+
+fn write_quoted(out: &mut String, text: &str, quote: char) {
+    out.push(quote);
+    for c in text.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\\' => out.push_str("\\\\"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+}
+
+fn write_items<'a>(
+    out: &mut String,
+    open: char,
+    close: char,
+    items: impl IntoIterator<Item = &'a Val>,
+) {
+    out.push(open);
+    for (i, val) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_val(out, val);
+    }
+    out.push(close);
+}
+
+fn write_case(out: &mut String, name: &str, payload: Option<&Val>) {
+    out.push_str(name);
+    if let Some(payload) = payload {
+        write_items(out, '(', ')', [payload]);
+    }
+}
+
+fn write_val(out: &mut String, val: &Val) {
+    match val {
+        Val::Bool(b) => {
+            let _ = write!(out, "{b}");
+        }
+        Val::S8(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Val::U8(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Val::S16(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Val::U16(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Val::S32(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Val::U32(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Val::S64(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Val::U64(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Val::Float32(x) => write_float(out, *x),
+        Val::Float64(x) => write_float(out, *x),
+        Val::Char(c) => write_quoted(out, &c.to_string(), '\''),
+        Val::String(s) => write_quoted(out, s, '"'),
+        Val::List(list) => write_items(out, '[', ']', list.iter()),
+        Val::Tuple(tuple) => write_items(out, '(', ')', tuple.values()),
+        Val::Record(record) => {
+            out.push('{');
+            for (i, (name, val)) in record.fields().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                out.push_str(&label(name));
+                out.push_str(": ");
+                write_val(out, val);
+            }
+            out.push('}');
+        }
+        Val::Variant(variant) => write_case(out, &label(variant.discriminant()), variant.payload()),
+        Val::Enum(enum_) => out.push_str(&label(enum_.discriminant())),
+        Val::Option(option) => match option.value() {
+            Some(val) => write_case(out, "some", Some(val)),
+            None => out.push_str("none"),
+        },
+        Val::Result(result) => match result.value() {
+            Ok(val) => write_case(out, "ok", val),
+            Err(val) => write_case(out, "err", val),
+        },
+        Val::Flags(flags) => {
+            let names: Vec<_> = flags.flags().map(label).collect();
+            let _ = write!(out, "{{{}}}", names.join(", "));
+        }
+        Val::Resource(_) => out.push_str("<resource>"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A component with one function per type under test, each taking a
+    /// single parameter of that type. The functions are never called, so
+    /// the core functions behind them only need matching signatures.
+    const TYPES: &str = r#"
+        (component
+          (core module $m
+            (memory (export "memory") 1)
+            (func (export "realloc") (param i32 i32 i32 i32) (result i32) unreachable)
+            (func (export "i") (param i32) unreachable)
+            (func (export "I") (param i64) unreachable)
+            (func (export "f") (param f32) unreachable)
+            (func (export "F") (param f64) unreachable)
+            (func (export "ii") (param i32 i32) unreachable)
+            (func (export "iii") (param i32 i32 i32) unreachable)
+            (func (export "iiii") (param i32 i32 i32 i32) unreachable)
+          )
+          (core instance $i (instantiate $m))
+          (alias core export $i "memory" (core memory $memory))
+          (alias core export $i "realloc" (core func $realloc))
+          (type $record (record (field "name" string) (field "ok" (option u32))))
+          (export $record' "record-type" (type $record))
+          (type $variant (variant (case "none") (case "num" u32) (case "text" string)))
+          (export $variant' "variant-type" (type $variant))
+          (type $enum (enum "red" "true"))
+          (export $enum' "enum-type" (type $enum))
+          (type $flags (flags "read" "write" "false"))
+          (export $flags' "flags-type" (type $flags))
+          (func (export "bool") (param "x" bool) (canon lift (core func $i "i")))
+          (func (export "s8") (param "x" s8) (canon lift (core func $i "i")))
+          (func (export "u8") (param "x" u8) (canon lift (core func $i "i")))
+          (func (export "s16") (param "x" s16) (canon lift (core func $i "i")))
+          (func (export "u16") (param "x" u16) (canon lift (core func $i "i")))
+          (func (export "s32") (param "x" s32) (canon lift (core func $i "i")))
+          (func (export "u32") (param "x" u32) (canon lift (core func $i "i")))
+          (func (export "s64") (param "x" s64) (canon lift (core func $i "I")))
+          (func (export "u64") (param "x" u64) (canon lift (core func $i "I")))
+          (func (export "f32") (param "x" float32) (canon lift (core func $i "f")))
+          (func (export "f64") (param "x" float64) (canon lift (core func $i "F")))
+          (func (export "char") (param "x" char) (canon lift (core func $i "i")))
+          (func (export "string") (param "x" string)
+            (canon lift (core func $i "ii") (memory $memory) (realloc $realloc)))
+          (func (export "list") (param "x" (list u8))
+            (canon lift (core func $i "ii") (memory $memory) (realloc $realloc)))
+          (func (export "tuple") (param "x" (tuple u32 string))
+            (canon lift (core func $i "iii") (memory $memory) (realloc $realloc)))
+          (func (export "record") (param "x" $record')
+            (canon lift (core func $i "iiii") (memory $memory) (realloc $realloc)))
+          (func (export "variant") (param "x" $variant')
+            (canon lift (core func $i "iii") (memory $memory) (realloc $realloc)))
+          (func (export "enum") (param "x" $enum') (canon lift (core func $i "i")))
+          (func (export "option") (param "x" (option string))
+            (canon lift (core func $i "iii") (memory $memory) (realloc $realloc)))
+          (func (export "result") (param "x" (result u32 (error string)))
+            (canon lift (core func $i "iii") (memory $memory) (realloc $realloc)))
+          (func (export "empty-result") (param "x" (result)) (canon lift (core func $i "i")))
+          (func (export "flags") (param "x" $flags') (canon lift (core func $i "i")))
+        )
+    "#;
+
+    /// The parameter type of the function `name` in [`TYPES`].
+    fn ty(name: &str) -> Type {
+        let mut config = wasmtime::Config::new();
+        config.wasm_component_model(true);
+        let engine = wasmtime::Engine::new(&config).unwrap();
+        let component = wasmtime::component::Component::new(&engine, TYPES).unwrap();
+        let linker = wasmtime::component::Linker::new(&engine);
+        let mut store = wasmtime::Store::new(&engine, ());
+        let instance = linker.instantiate(&mut store, &component).unwrap();
+        let func = instance.get_func(&mut store, name).unwrap();
+        func.params(&store)[0].clone()
+    }
+
+    /// `src` read as a `name` and printed back.
+    fn reprint(name: &str, src: &str) -> String {
+        match parse(&ty(name), src) {
+            Ok(val) => print(&val),
+            Err(e) => panic!("{src:?} failed to parse as {name}: {e:#}"),
+        }
+    }
+
+    /// Asserts that each of `srcs` prints back as itself.
+    fn round_trip(name: &str, srcs: &[&str]) {
+        for src in srcs {
+            assert_eq!(reprint(name, src), *src);
+        }
+    }
+
+    fn error(name: &str, src: &str) -> String {
+        match parse(&ty(name), src) {
+            Err(e) => format!("{e:#}"),
+            Ok(val) => panic!("{src:?} parsed as {}", print(&val)),
+        }
+    }
+
+    #[test]
+    fn bools() {
+        round_trip("bool", &["true", "false"]);
+        assert_eq!(
+            error("bool", "yes"),
+            "expected `true` or `false` at column 4"
+        );
+    }
+
+    #[test]
+    fn integers() {
+        round_trip("s8", &["-128", "127"]);
+        round_trip("u8", &["0", "255"]);
+        round_trip("s16", &["-32768"]);
+        round_trip("u16", &["65535"]);
+        round_trip("s32", &["-2147483648"]);
+        round_trip("u32", &["4294967295"]);
+        round_trip("s64", &["-9223372036854775808"]);
+        round_trip("u64", &["18446744073709551615"]);
+        assert_eq!(error("u8", "256"), "expected u8 at column 1");
+        assert_eq!(error("u32", "-1"), "expected u32 at column 1");
+    }
+
+    #[test]
+    fn floats() {
+        round_trip("f32", &["0.1", "-1.5", "16777216", "inf", "-inf", "nan"]);
+        round_trip("f64", &["0.1", "-1.5", "inf", "-inf", "nan"]);
+        assert_eq!(reprint("f64", "1e3"), "1000");
+        assert_eq!(error("f32", "x"), "expected a number at column 1");
+    }
+
+    #[test]
+    fn chars() {
+        round_trip("char", &["'x'", "'\\''", "'\\n'", "'\\u{7f}'", "'é'"]);
+        assert_eq!(reprint("char", r"'\u{48}'"), "'H'");
+        assert_eq!(error("char", "'ab'"), "expected one character at column 5");
+    }
+
+    #[test]
+    fn strings() {
+        round_trip(
+            "string",
+            &[r#""""#, r#""a \"b\" \\ c\td\r\n""#, r#""\u{1}""#],
+        );
+        assert_eq!(
+            reprint("string", r#""\u{48}i\u{1F600}""#),
+            "\"Hi\u{1F600}\""
+        );
+        assert_eq!(reprint("string", r#""it's""#), r#""it's""#);
+        assert_eq!(
+            error("string", r#""abc"#),
+            "missing closing `\"` at column 5"
+        );
+        assert_eq!(
+            error("string", r#""\u{d800}""#),
+            "bad `\\u{...}` escape at column 10"
+        );
+        assert_eq!(error("string", r#""\q""#), "unknown escape at column 4");
+    }
+
+    #[test]
+    fn bare_string_arguments_are_literal() {
+        let val = parse_arg(&Type::String, 0, r"a \n b").unwrap();
+        assert_eq!(print(&val), r#""a \\n b""#);
+        let val = parse_arg(&Type::String, 0, r#""a \n b""#).unwrap();
+        assert_eq!(print(&val), r#""a \n b""#);
+    }
+
+    #[test]
+    fn lists() {
+        round_trip("list", &["[]", "[1]", "[1, 2, 3]"]);
+        assert_eq!(reprint("list", "[ 1 ,2, ]"), "[1, 2]");
+        assert_eq!(error("list", "[1 2]"), "expected `]` at column 4");
+    }
+
+    #[test]
+    fn tuples() {
+        round_trip("tuple", &[r#"(1, "a")"#]);
+        assert_eq!(
+            error("tuple", "(1)"),
+            "expected tuple<u32, string> at column 4"
+        );
+        assert_eq!(
+            error("tuple", r#"(1, "a", 2)"#),
+            "too many tuple elements at column 10"
+        );
+    }
+
+    #[test]
+    fn records() {
+        round_trip(
+            "record",
+            &[r#"{name: "x", %ok: some(3)}"#, r#"{name: "x", %ok: none}"#],
+        );
+        // Fields may come in any order, and optional ones may be left out.
+        assert_eq!(
+            reprint("record", r#"{%ok: some(3), name: "x"}"#),
+            r#"{name: "x", %ok: some(3)}"#
+        );
+        assert_eq!(
+            reprint("record", r#"{name: "x"}"#),
+            r#"{name: "x", %ok: none}"#
+        );
+        assert_eq!(error("record", "{}"), "missing field `name` at column 3");
+        assert_eq!(error("record", "{size: 1}"), "no field `size` at column 6");
+        assert_eq!(
+            error("record", r#"{name: "x", name: "y"}"#),
+            "field `name` given twice at column 17"
+        );
+    }
+
+    #[test]
+    fn variants() {
+        round_trip("variant", &["%none", "num(7)", r#"text("hi")"#]);
+        assert_eq!(reprint("variant", "none"), "%none");
+        assert_eq!(
+            error("variant", "other"),
+            "expected variant {none, num, text} at column 6"
+        );
+        assert_eq!(error("variant", "num"), "expected `(` at column 4");
+    }
+
+    #[test]
+    fn enums() {
+        round_trip("enum", &["red", "%true"]);
+        assert_eq!(
+            error("enum", "blue"),
+            "expected enum {red, true} at column 5"
+        );
+    }
+
+    #[test]
+    fn options() {
+        round_trip("option", &["none", r#"some("x")"#]);
+        assert_eq!(
+            error("option", "x"),
+            "expected `some(...)` or `none` at column 2"
+        );
+    }
+
+    #[test]
+    fn results() {
+        round_trip("result", &["ok(1)", r#"err("no")"#]);
+        round_trip("empty-result", &["ok", "err"]);
+        assert_eq!(error("result", "ok"), "expected `(` at column 3");
+    }
+
+    #[test]
+    fn flags() {
+        round_trip("flags", &["{}", "{read}", "{read, write, %false}"]);
+        assert_eq!(reprint("flags", "{false, read}"), "{read, %false}");
+        assert_eq!(
+            error("flags", "{exec}"),
+            "expected flags {read, write, false} at column 7"
+        );
+    }
+
+    #[test]
+    fn trailing_text_is_an_error() {
+        assert_eq!(
+            error("u8", "1 2"),
+            "unexpected text after the value at column 3"
+        );
+    }
+}